@@ -0,0 +1,161 @@
+//! Pods something is still happening to, going by the Events about them.
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{offset, DateTime, Duration, Utc};
+use k8s_openapi::api::core::v1::Event;
+use kube::{
+    api::{Api, ListParams},
+    Client,
+};
+
+use crate::{error::Result, format_age, scan::PodTarget};
+
+/// When anything last happened according to the event, whichever of its
+/// timestamps is set.
+fn last_seen(event: &Event) -> Option<DateTime<Utc>> {
+    [
+        event.last_timestamp.as_ref().map(|t| t.0),
+        event.event_time.as_ref().map(|t| t.0),
+        event
+            .series
+            .as_ref()
+            .and_then(|s| s.last_observed_time.as_ref())
+            .map(|t| t.0),
+        event.metadata.creation_timestamp.as_ref().map(|t| t.0),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+}
+
+/// Drops the targets with an Event newer than `within`, they're probably
+/// still being started or debugged.
+///
+/// Events are listed once per namespace. When that fails the namespace's
+/// targets are dropped too, the point is to not delete pods in doubt.
+pub async fn skip_recently_active(
+    client: Client,
+    targets: Vec<PodTarget>,
+    within: Duration,
+) -> Result<Vec<PodTarget>> {
+    let mut by_namespace: BTreeMap<String, Vec<PodTarget>> = BTreeMap::new();
+    for target in targets {
+        by_namespace
+            .entry(target.namespace())
+            .or_default()
+            .push(target);
+    }
+
+    let now = offset::Utc::now();
+    let mut kept = Vec::new();
+    for (ns, targets) in by_namespace {
+        let events: Api<Event> = Api::namespaced(client.clone(), &ns);
+        let listed = match events
+            .list(&ListParams::default().fields("involvedObject.kind=Pod"))
+            .await
+        {
+            Ok(listed) => listed,
+            Err(e) => {
+                tracing::warn!(
+                    "Unable to list events in {}, skipping its {} pods: {}",
+                    ns,
+                    targets.len(),
+                    e
+                );
+                continue;
+            }
+        };
+        // by uid, a pod recreated under the same name has its own events
+        let mut latest: HashMap<String, DateTime<Utc>> = HashMap::new();
+        for event in listed.iter() {
+            if let (Some(uid), Some(seen)) = (&event.involved_object.uid, last_seen(event)) {
+                let entry = latest.entry(uid.clone()).or_insert(seen);
+                *entry = (*entry).max(seen);
+            }
+        }
+
+        for target in targets {
+            let seen = target
+                .pod
+                .metadata
+                .uid
+                .as_ref()
+                .and_then(|uid| latest.get(uid));
+            match seen {
+                Some(seen) if now - *seen <= within => tracing::info!(
+                    "Skipping {}:{}, last event {} ago",
+                    ns,
+                    target.name(),
+                    format_age(now - *seen)
+                ),
+                _ => kept.push(target),
+            }
+        }
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, Duration};
+    use serde_json::json;
+
+    use super::skip_recently_active;
+    use crate::{
+        scan::PodTarget,
+        testing::{json_response, mock_client, pod},
+    };
+
+    fn target(name: &str) -> PodTarget {
+        let mut p = pod("apps", name, Some(offset::Utc::now() - Duration::days(30)));
+        p.metadata.uid = Some(format!("{}-uid", name));
+        PodTarget {
+            pod: p,
+            reason: "age 30d0h > 3d0h".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn pods_with_recent_events_are_skipped() {
+        let (client, mut handle) = mock_client();
+        let now = offset::Utc::now();
+
+        let server = tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.expect("list not sent");
+            assert_eq!(request.uri().path(), "/api/v1/namespaces/apps/events");
+            assert!(request
+                .uri()
+                .query()
+                .unwrap()
+                .contains("fieldSelector=involvedObject.kind%3DPod"));
+            let event = |uid: &str, at: chrono::DateTime<chrono::Utc>| {
+                json!({
+                    "metadata": { "name": format!("{}.1", uid), "namespace": "apps" },
+                    "involvedObject": { "kind": "Pod", "uid": uid },
+                    "lastTimestamp": at.to_rfc3339(),
+                })
+            };
+            send.send_response(json_response(
+                200,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "EventList",
+                    "metadata": {},
+                    "items": [
+                        event("busy-uid", now - Duration::minutes(2)),
+                        event("quiet-uid", now - Duration::hours(5)),
+                    ],
+                }),
+            ));
+        });
+
+        let targets = vec![target("busy"), target("quiet"), target("silent")];
+        let kept = skip_recently_active(client, targets, Duration::minutes(30))
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        let names: Vec<String> = kept.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["quiet", "silent"]);
+    }
+}
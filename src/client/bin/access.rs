@@ -0,0 +1,41 @@
+//! Permission checks against the apiserver, so RBAC problems surface once up
+//! front instead of as a 403 per pod.
+use color_eyre::eyre::{Result, WrapErr};
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+};
+use kube::{
+    api::{Api, PostParams},
+    Client,
+};
+
+/// Asks the apiserver whether the current identity may `verb` the `resource`
+/// (e.g. `pods`) of API `group` (`""` for the core one), in `namespace` or
+/// across the cluster when it is `None`.
+pub async fn can_i(
+    client: Client,
+    verb: &str,
+    group: &str,
+    resource: &str,
+    namespace: Option<&str>,
+) -> Result<bool> {
+    let review = SelfSubjectAccessReview {
+        spec: SelfSubjectAccessReviewSpec {
+            resource_attributes: Some(ResourceAttributes {
+                namespace: namespace.map(ToString::to_string),
+                verb: Some(verb.to_string()),
+                group: Some(group.to_string()),
+                resource: Some(resource.to_string()),
+                ..ResourceAttributes::default()
+            }),
+            ..SelfSubjectAccessReviewSpec::default()
+        },
+        ..SelfSubjectAccessReview::default()
+    };
+
+    let response = Api::<SelfSubjectAccessReview>::all(client)
+        .create(&PostParams::default(), &review)
+        .await
+        .wrap_err("Unable to create SelfSubjectAccessReview")?;
+    Ok(response.status.map_or(false, |s| s.allowed))
+}
@@ -0,0 +1,199 @@
+//! Append-only JSON lines record of every deletion shopvac attempts, and
+//! `--audit-annotate` marking pods with the reason right before deleting them.
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::offset;
+use color_eyre::eyre::{Result, WrapErr};
+use k8s_openapi::api::{
+    apps::v1::ReplicaSet,
+    core::v1::{PersistentVolumeClaim, Pod},
+};
+use kube::{
+    api::{Api, DynamicObject, Patch, PatchParams},
+    Client, Resource, ResourceExt,
+};
+use serde::Serialize;
+use serde_json::json;
+use shopvac::{Outcome, PodTarget};
+
+/// Annotation `--audit-annotate` records the deletion reason in
+pub const REASON_ANNOTATION: &str = "shopvac.io/deleted-by-policy";
+
+/// The filters that were in effect for a run, recorded alongside each entry
+/// so the audit trail explains why a pod was selected.
+#[derive(Debug, Clone, Serialize)]
+pub struct Filters {
+    pub namespace: Option<String>,
+    pub label_selector: Option<String>,
+    pub field_selector: Option<String>,
+    pub older_than_hours: i64,
+    pub completed_for_seconds: Option<u64>,
+    pub exclude_namespace_pattern: String,
+    pub annotation_selectors: Vec<String>,
+    pub phases: Vec<String>,
+    pub reasons: Vec<String>,
+    pub protection_annotation: Option<String>,
+    pub image_pattern: Option<String>,
+    pub orphaned: bool,
+    pub skip_recently_active_seconds: Option<u64>,
+    pub exclude_running_longer_than_seconds: Option<u64>,
+    pub exclude_pod_pattern: Option<String>,
+    pub owner_kinds: Vec<String>,
+    pub naked_only: bool,
+}
+
+/// Anything shopvac deletes, `status.phase` is the one non-metadata field
+/// recorded for it.
+pub trait Audited: Resource {
+    /// The kind recorded for the object, which only the object itself knows
+    /// for a `--gvk` resource.
+    fn kind_name(&self) -> String;
+    fn phase(&self) -> Option<&str>;
+}
+
+impl Audited for Pod {
+    fn kind_name(&self) -> String {
+        Self::kind(&()).to_string()
+    }
+
+    fn phase(&self) -> Option<&str> {
+        self.status.as_ref().and_then(|s| s.phase.as_deref())
+    }
+}
+
+impl Audited for PersistentVolumeClaim {
+    fn kind_name(&self) -> String {
+        Self::kind(&()).to_string()
+    }
+
+    fn phase(&self) -> Option<&str> {
+        self.status.as_ref().and_then(|s| s.phase.as_deref())
+    }
+}
+
+impl Audited for ReplicaSet {
+    fn kind_name(&self) -> String {
+        Self::kind(&()).to_string()
+    }
+
+    fn phase(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Audited for DynamicObject {
+    fn kind_name(&self) -> String {
+        self.types
+            .as_ref()
+            .map(|t| t.kind.clone())
+            .unwrap_or_default()
+    }
+
+    fn phase(&self) -> Option<&str> {
+        None
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    kind: String,
+    namespace: Option<String>,
+    pod: String,
+    uid: Option<String>,
+    age_hours: Option<i64>,
+    phase: Option<&'a str>,
+    owner: Option<String>,
+    filters: &'a Filters,
+    dry_run: bool,
+    #[serde(flatten)]
+    outcome: &'a Outcome,
+}
+
+pub struct AuditLog {
+    writer: BufWriter<File>,
+    filters: Filters,
+    dry_run: bool,
+}
+
+impl AuditLog {
+    /// Opens (or creates) the audit log for appending, failing up front if the
+    /// path isn't writable so we never delete anything without a record.
+    pub fn open(path: &Path, filters: Filters, dry_run: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("Unable to open audit log {}", path.display()))?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            filters,
+            dry_run,
+        })
+    }
+
+    pub fn record<K: Audited>(&mut self, pod: &K, outcome: &Outcome) -> Result<()> {
+        let now = offset::Utc::now();
+        let owner = pod
+            .owner_references()
+            .first()
+            .map(|o| format!("{}/{}", o.kind, o.name));
+
+        let record = Record {
+            timestamp: now.to_rfc3339(),
+            kind: pod.kind_name(),
+            namespace: pod.namespace(),
+            pod: pod.name(),
+            uid: pod.uid(),
+            age_hours: pod
+                .metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|ct| (now - ct.0).num_hours()),
+            phase: pod.phase(),
+            owner,
+            filters: &self.filters,
+            dry_run: self.dry_run,
+            outcome,
+        };
+
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush().wrap_err("Unable to flush audit log")
+    }
+}
+
+/// Patches the reason the pod was selected onto it as an annotation, so
+/// anything watching pod mutations sees why it is about to go away.
+///
+/// Failures are logged and otherwise ignored, like a missing log archive.
+pub async fn annotate(client: Client, target: &PodTarget, dry_run: bool) {
+    let (ns, name) = (target.namespace(), target.name());
+    let pods: Api<Pod> = Api::namespaced(client, &ns);
+    let params = PatchParams {
+        dry_run,
+        ..PatchParams::default()
+    };
+    let patch = json!({
+        "metadata": {
+            "annotations": { REASON_ANNOTATION: target.reason },
+        }
+    });
+    if let Err(e) = pods.patch(&name, &params, &Patch::Merge(&patch)).await {
+        tracing::warn!(
+            "Unable to annotate {}:{} before deleting it: {}",
+            ns,
+            name,
+            e
+        );
+    }
+}
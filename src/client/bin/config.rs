@@ -0,0 +1,264 @@
+//! Optional config file whose keys mirror the CLI flags.
+//!
+//! Precedence is CLI flag > environment variable > config file > default, a
+//! file value is only applied when clap reports the flag wasn't explicitly
+//! provided.
+use std::{fs, path::Path, path::PathBuf};
+
+use clap::{ArgMatches, ValueSource};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde::Deserialize;
+
+use shopvac::{
+    filter::{AnnotationSelector, PodPhase},
+    Gvk,
+};
+
+use crate::{
+    notify::NotifyOn, output::OutputFormat, watch::Interval, Args, DeleteOrder, DryRun,
+    Propagation, ResourceKind,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    quiet: Option<bool>,
+    verbose: Option<bool>,
+    log_level: Option<String>,
+    namespace: Option<String>,
+    resource: Option<ResourceKind>,
+    gvk: Option<Gvk>,
+    all_namespaces: Option<bool>,
+    namespace_from_file: Option<PathBuf>,
+    kubeconfig: Option<PathBuf>,
+    context: Option<String>,
+    request_timeout: Option<Interval>,
+    #[serde(rename = "as")]
+    as_user: Option<String>,
+    as_group: Option<Vec<String>>,
+    health_check: Option<bool>,
+    older_than: Option<u32>,
+    older_than_hours: Option<u32>,
+    completed_for: Option<Interval>,
+    label_selector: Option<String>,
+    not_label: Option<Vec<String>>,
+    field_selector: Option<String>,
+    actually_delete: Option<bool>,
+    server_dry_run: Option<bool>,
+    dry_run: Option<DryRun>,
+    exclude_namespace_pattern: Option<String>,
+    allow_system_namespaces: Option<bool>,
+    phase: Option<Vec<PodPhase>>,
+    reason: Option<Vec<String>>,
+    image_pattern: Option<String>,
+    orphaned: Option<bool>,
+    skip_recently_active: Option<Interval>,
+    exclude_running_longer_than: Option<Interval>,
+    exclude_pod_pattern: Option<String>,
+    owner_kind: Option<Vec<String>>,
+    naked_only: Option<bool>,
+    explain: Option<bool>,
+    protection_annotation: Option<String>,
+    annotation_selector: Option<Vec<String>>,
+    sample: Option<u8>,
+    max_pods: Option<usize>,
+    grace_period: Option<u32>,
+    propagation_policy: Option<Propagation>,
+    verbose_dry_run: Option<bool>,
+    rate: Option<u32>,
+    batch_size: Option<u32>,
+    batch_delay: Option<Interval>,
+    chunk_size: Option<u32>,
+    watch: Option<bool>,
+    interval: Option<Interval>,
+    delete_order: Option<DeleteOrder>,
+    plan: Option<bool>,
+    count_only: Option<bool>,
+    output: Option<OutputFormat>,
+    diff_against: Option<PathBuf>,
+    audit_log: Option<PathBuf>,
+    archive_logs: Option<PathBuf>,
+    audit_annotate: Option<bool>,
+    state_file: Option<PathBuf>,
+    report_configmap: Option<String>,
+    notify_webhook: Option<String>,
+    notify_on: Option<NotifyOn>,
+    notify_threshold: Option<usize>,
+    metrics_pushgateway: Option<String>,
+    metrics_textfile: Option<PathBuf>,
+}
+
+impl Config {
+    /// Loads a TOML or YAML config, picked by the file extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Unable to read config file {}", path.display()))?;
+
+        let config = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&raw).map_err(color_eyre::eyre::Error::from),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&raw).map_err(color_eyre::eyre::Error::from)
+            }
+            _ => bail!(
+                "Config file {} must have a .toml, .yaml or .yml extension",
+                path.display()
+            ),
+        };
+        config.wrap_err_with(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Applies file values to any argument that wasn't set on the command line
+    /// or through the environment.
+    pub fn merge_into(self, args: &mut Args, matches: &ArgMatches) -> Result<()> {
+        let explicit = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable)
+            )
+        };
+
+        macro_rules! merge {
+            ($($field:ident),* $(,)?) => {$(
+                if let Some(value) = self.$field {
+                    if !explicit(&stringify!($field).replace('_', "-")) {
+                        args.$field = value.into();
+                    }
+                }
+            )*};
+        }
+
+        merge!(
+            quiet,
+            verbose,
+            log_level,
+            namespace,
+            resource,
+            gvk,
+            all_namespaces,
+            namespace_from_file,
+            kubeconfig,
+            context,
+            request_timeout,
+            as_user,
+            as_group,
+            health_check,
+            older_than,
+            older_than_hours,
+            completed_for,
+            label_selector,
+            not_label,
+            field_selector,
+            actually_delete,
+            server_dry_run,
+            dry_run,
+            exclude_namespace_pattern,
+            allow_system_namespaces,
+            phase,
+            reason,
+            image_pattern,
+            orphaned,
+            skip_recently_active,
+            exclude_running_longer_than,
+            exclude_pod_pattern,
+            owner_kind,
+            naked_only,
+            explain,
+            protection_annotation,
+            sample,
+            max_pods,
+            grace_period,
+            propagation_policy,
+            verbose_dry_run,
+            rate,
+            batch_size,
+            batch_delay,
+            chunk_size,
+            watch,
+            interval,
+            delete_order,
+            plan,
+            count_only,
+            output,
+            diff_against,
+            audit_log,
+            archive_logs,
+            audit_annotate,
+            state_file,
+            report_configmap,
+            notify_webhook,
+            notify_on,
+            notify_threshold,
+            metrics_pushgateway,
+            metrics_textfile,
+        );
+
+        if let Some(selectors) = self.annotation_selector {
+            if !explicit("annotation-selector") {
+                args.annotation_selector = selectors
+                    .iter()
+                    .map(|s| s.parse::<AnnotationSelector>())
+                    .collect::<Result<_, _>>()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::testing::{parse, parse_with_env};
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn file_beats_the_default() {
+        let (mut args, matches) = parse(&[]);
+        config("older-than-hours = 12\nexclude-namespace-pattern = \"^infra-\"")
+            .merge_into(&mut args, &matches)
+            .unwrap();
+        assert_eq!(args.older_than_hours, 12);
+        assert_eq!(args.exclude_namespace_pattern, "^infra-");
+    }
+
+    #[test]
+    fn env_beats_the_file() {
+        let (mut args, matches) = parse_with_env(
+            &[
+                ("SHOPVAC_OLDER_THAN_HOURS", "24"),
+                ("SHOPVAC_NAMESPACE", "from-env"),
+            ],
+            &[],
+        );
+        config("older-than-hours = 12\nnamespace = \"from-file\"")
+            .merge_into(&mut args, &matches)
+            .unwrap();
+        assert_eq!(args.older_than_hours, 24);
+        assert_eq!(args.namespace.as_deref(), Some("from-env"));
+    }
+
+    #[test]
+    fn flag_beats_env_and_file() {
+        let (mut args, matches) = parse_with_env(
+            &[
+                ("SHOPVAC_OLDER_THAN_HOURS", "24"),
+                ("SHOPVAC_NAMESPACE", "from-env"),
+            ],
+            &["--older-than-hours", "6", "--namespace", "from-flag"],
+        );
+        config("older-than-hours = 12\nnamespace = \"from-file\"")
+            .merge_into(&mut args, &matches)
+            .unwrap();
+        assert_eq!(args.older_than_hours, 6);
+        assert_eq!(args.namespace.as_deref(), Some("from-flag"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = toml::from_str::<Config>("older-than-hours = 12\nolder-then = 3").unwrap_err();
+        assert!(err.to_string().contains("older-then"), "{}", err);
+    }
+}
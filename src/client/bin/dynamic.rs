@@ -0,0 +1,62 @@
+//! `--gvk`, cleaning up any namespaced resource by its age.
+use chrono::offset;
+use color_eyre::eyre::Result;
+use kube::{
+    api::{Api, DynamicObject},
+    Client, ResourceExt,
+};
+use shopvac::{Gvk, ScanOptions};
+
+use crate::{
+    audit::AuditLog,
+    objects::{clean_found, reject_pod_flags},
+    report::RunSummary,
+    Args,
+};
+
+pub async fn clean(
+    args: &Args,
+    client: Client,
+    gvk: &Gvk,
+    scan: &ScanOptions,
+    target: &str,
+    summary: &mut RunSummary,
+    audit: &mut Option<AuditLog>,
+) -> Result<()> {
+    reject_pod_flags(args)?;
+
+    let resource = shopvac::discover(client.clone(), gvk).await?;
+    let api: Api<DynamicObject> = match &args.namespace {
+        Some(ns) => Api::namespaced_with(client.clone(), ns, &resource),
+        None => Api::all_with(client.clone(), &resource),
+    };
+
+    let found = shopvac::scan_dynamic(&api, &resource, scan).await?;
+    let now = offset::Utc::now();
+    for obj in &found {
+        let age = obj
+            .metadata
+            .creation_timestamp
+            .as_ref()
+            .map_or(0, |ct| (now - ct.0).num_hours());
+        tracing::info!(
+            "Found old {}! {}:{}, duration: {:?} hours old",
+            resource.kind,
+            obj.namespace().unwrap_or_default(),
+            obj.name(),
+            age
+        );
+    }
+
+    clean_found(
+        args,
+        client,
+        &found,
+        resource.clone(),
+        &resource.plural,
+        target,
+        summary,
+        audit,
+    )
+    .await
+}
@@ -0,0 +1,62 @@
+//! `--archive-logs`, saving the container logs of pods before they're deleted.
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{Result, WrapErr};
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{Api, LogParams},
+    Client,
+};
+use shopvac::PodTarget;
+
+/// Creates the archive directory up front, so a bad path fails the run before
+/// anything is deleted.
+pub fn prepare(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .wrap_err_with(|| format!("Unable to create log archive directory {}", dir.display()))
+}
+
+/// Writes the logs of every container of the pod to
+/// `<dir>/<namespace>_<pod>_<container>.log`.
+///
+/// Failures are logged and otherwise ignored, a missing archive never holds
+/// up the delete.
+pub async fn archive(client: Client, dir: &Path, target: &PodTarget) {
+    let (ns, name) = (target.namespace(), target.name());
+    let pods: Api<Pod> = Api::namespaced(client, &ns);
+
+    // metadata only scans don't know the containers, the pod's default
+    // container is the best we can do then
+    let containers: Vec<Option<String>> = match &target.pod.spec {
+        Some(spec) => spec
+            .containers
+            .iter()
+            .map(|c| Some(c.name.clone()))
+            .collect(),
+        None => vec![None],
+    };
+    for container in containers {
+        let lp = LogParams {
+            container: container.clone(),
+            ..LogParams::default()
+        };
+        let path = dir.join(match &container {
+            Some(c) => format!("{}_{}_{}.log", ns, name, c),
+            None => format!("{}_{}.log", ns, name),
+        });
+        let written = match pods.logs(&name, &lp).await {
+            Ok(logs) => fs::write(&path, logs).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match written {
+            Ok(()) => tracing::debug!("Archived logs of {}:{} to {}", ns, name, path.display()),
+            Err(e) => tracing::warn!(
+                "Unable to archive logs of {}:{}{}: {}",
+                ns,
+                name,
+                container.map(|c| format!(" ({})", c)).unwrap_or_default(),
+                e
+            ),
+        }
+    }
+}
@@ -3,145 +3,1710 @@
 ///
 /// It has been used with some success in clearing out stuff like Tekton
 /// leaving old builds behind, Airflow being messy, etc.
-use chrono::offset;
-use clap::Parser;
-use futures::stream::{self, StreamExt};
-use k8s_openapi::api::core::v1::Pod;
+use std::{
+    cell::{Cell, RefCell},
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use chrono::{offset, DateTime, Duration, Utc};
+use clap::{CommandFactory, ErrorKind, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use futures::{
+    future,
+    stream::{self, StreamExt},
+};
+use k8s_openapi::{
+    api::core::v1::{ConfigMap, Pod},
+    apimachinery::pkg::apis::meta::v1::OwnerReference,
+};
 use kube::{
-    api::{Api, DeleteParams, ListParams, ResourceExt},
+    api::{Api, DeleteParams, PropagationPolicy},
+    config::{KubeConfigOptions, Kubeconfig},
     Client,
 };
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time,
+};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{bail, Result, WrapErr};
+
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use shopvac::{
+    filter::{is_system_namespace, AnnotationSelector, PodPhase},
+    DeleteOptions, Gvk, Outcome, PodTarget, Requests, ScanOptions, ScanReport, ShopvacError,
+};
 
-use regex::Regex;
 use tracing::metadata::LevelFilter;
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, EnvFilter};
+
+mod access;
+mod audit;
+mod config;
+mod dynamic;
+mod logs;
+mod metrics;
+mod notify;
+mod objects;
+mod output;
+mod presets;
+mod pvc;
+mod replicaset;
+mod report;
+mod state;
+#[cfg(test)]
+mod testing;
+mod watch;
+
+use audit::{AuditLog, Filters};
+use config::Config;
+use notify::NotifyOn;
+use output::OutputFormat;
+use presets::{PresetName, PRESETS};
+use report::RunSummary;
+use watch::{Interval, PodSource};
 
 /// Pod bulk deletion tool
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = ENV_HELP
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Deprecated, the same as `shopvac clean`
+    #[clap(flatten)]
+    args: Args,
+}
+
+const ENV_HELP: &str = "Every flag can also be set through the SHOPVAC_* environment variable \
+                        shown next to it. Flags given on the command line take precedence over \
+                        the environment, which takes precedence over --config and then the \
+                        defaults.";
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Find matching pods and delete them, a dry run unless --actually-delete
+    /// is given
+    #[clap(after_help = ENV_HELP)]
+    Clean(Box<Args>),
+    /// Find matching pods and list them, never deletes anything
+    #[clap(after_help = ENV_HELP)]
+    Scan(Box<Args>),
+    /// Print a shell completion script to stdout
+    #[clap(hide = true)]
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
+    /// Read defaults from a TOML or YAML file whose keys mirror these flags
+    #[clap(long, env = "SHOPVAC_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Start from the defaults of a known workload, explicit flags still win
+    #[clap(long, value_enum, env = "SHOPVAC_PRESET")]
+    preset: Option<PresetName>,
+
+    /// Only log warnings and errors
+    #[clap(short, long, conflicts_with = "verbose", env = "SHOPVAC_QUIET")]
+    quiet: bool,
+
+    /// Log everything, including trace output
+    #[clap(short, long, env = "SHOPVAC_VERBOSE")]
+    verbose: bool,
+
+    /// Log filter directives, e.g. `info,kube=debug`, ignored when --quiet or
+    /// --verbose is given
+    #[clap(long, value_name = "FILTER", env = "SHOPVAC_LOG")]
+    log_level: Option<String>,
+
     /// Namespace to scan pods for
-    #[clap(short, long)]
+    #[clap(short, long, env = "SHOPVAC_NAMESPACE")]
     namespace: Option<String>,
 
-    /// Remove pods that are older_than X days
-    #[clap(short, long, default_value_t = 3)]
-    older_than: i8,
+    /// Kind of object to clean up
+    ///
+    /// With `pvc` and `replicaset` only the namespace, selector, annotation
+    /// and age flags apply. Claims still mounted by a running pod are always
+    /// kept, as are ReplicaSets not scaled to zero or with live pods.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = ResourceKind::Pod,
+        env = "SHOPVAC_RESOURCE"
+    )]
+    resource: ResourceKind,
+
+    /// Clean up any namespaced resource instead, e.g. `batch/v1/Job` or
+    /// `v1/ConfigMap` for the core group
+    ///
+    /// Objects are picked by their creation timestamp alone, like with
+    /// `--resource pvc` only the namespace, selector, annotation and age flags
+    /// apply.
+    #[clap(
+        long,
+        value_name = "GROUP/VERSION/KIND",
+        conflicts_with = "resource",
+        env = "SHOPVAC_GVK"
+    )]
+    gvk: Option<Gvk>,
+
+    /// Scan pods in every namespace (cluster mode)
+    #[clap(
+        short = 'A',
+        long,
+        conflicts_with = "namespace",
+        env = "SHOPVAC_ALL_NAMESPACES"
+    )]
+    all_namespaces: bool,
+
+    /// Scan the namespaces listed in this file, one per line. Blank lines and
+    /// `#` comments are ignored
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &["namespace", "all-namespaces"],
+        env = "SHOPVAC_NAMESPACE_FROM_FILE"
+    )]
+    namespace_from_file: Option<PathBuf>,
+
+    /// The namespaces read from --namespace-from-file
+    #[clap(skip)]
+    namespaces: Vec<String>,
+
+    /// Skip the confirmation prompt when deleting across all namespaces
+    #[clap(short, long, env = "SHOPVAC_YES")]
+    yes: bool,
+
+    /// Path to the kubeconfig file to use instead of the default one
+    #[clap(long, env = "SHOPVAC_KUBECONFIG")]
+    kubeconfig: Option<PathBuf>,
+
+    /// Kubeconfig context to use instead of the current one
+    #[clap(long, env = "SHOPVAC_CONTEXT")]
+    context: Option<String>,
+
+    /// Timeout for connecting to the apiserver and for every read from it,
+    /// e.g. 30s, so a flaky network fails the run instead of hanging it
+    #[clap(
+        long,
+        value_name = "DURATION",
+        default_value = "30s",
+        env = "SHOPVAC_REQUEST_TIMEOUT"
+    )]
+    request_timeout: Interval,
+
+    /// User to impersonate for every request
+    #[clap(long = "as", value_name = "USER", env = "SHOPVAC_AS")]
+    as_user: Option<String>,
+
+    /// Group to impersonate for every request (repeatable)
+    #[clap(
+        long = "as-group",
+        value_name = "GROUP",
+        requires = "as-user",
+        env = "SHOPVAC_AS_GROUP"
+    )]
+    as_group: Vec<String>,
+
+    /// Check with SelfSubjectAccessReviews that pods (or PVCs) may be listed,
+    /// and deleted when deleting, in every target namespace before scanning.
+    /// Always done with --as
+    #[clap(long, env = "SHOPVAC_HEALTH_CHECK")]
+    health_check: bool,
+
+    /// Remove pods that are older_than X days, takes precedence over
+    /// --older-than-hours
+    #[clap(short, long, env = "SHOPVAC_OLDER_THAN")]
+    older_than: Option<u32>,
 
     /// Remove pods that are older_than X hours
-    #[clap(short, long, default_value_t = 72)]
-    older_than_hours: i8,
+    #[clap(short, long, default_value_t = 72, env = "SHOPVAC_OLDER_THAN_HOURS")]
+    older_than_hours: u32,
+
+    /// Judge Succeeded and Failed pods by the time since their last container
+    /// finished, e.g. 6h or 2d, instead of by their creation age
+    #[clap(long, value_name = "DURATION", env = "SHOPVAC_COMPLETED_FOR")]
+    completed_for: Option<Interval>,
 
     /// Label selector to use
-    #[clap(short, long)]
+    #[clap(short, long, env = "SHOPVAC_LABEL_SELECTOR")]
     label_selector: Option<String>,
 
+    /// Skip pods with this label, as `key=value` or just `key`, sent to the
+    /// apiserver as `key!=value` / `!key` (repeatable)
+    #[clap(long, value_name = "LABEL", env = "SHOPVAC_NOT_LABEL")]
+    not_label: Vec<String>,
+
     /// Field selector to use
-    #[clap(short, long)]
+    #[clap(short, long, env = "SHOPVAC_FIELD_SELECTOR")]
     field_selector: Option<String>,
 
     /// Whether or not to avoid a dry-run (the default)
-    #[clap(short, long)]
+    #[clap(short, long, env = "SHOPVAC_ACTUALLY_DELETE")]
     actually_delete: bool,
 
+    /// Send the deletes as apiserver dry runs, so admission and RBAC are
+    /// checked without removing anything
+    #[clap(
+        long,
+        conflicts_with = "actually-delete",
+        env = "SHOPVAC_SERVER_DRY_RUN"
+    )]
+    server_dry_run: bool,
+
+    /// Dry run mode: `client` only lists what would be deleted (the default
+    /// without --actually-delete), `server` is the same as --server-dry-run
+    #[clap(
+        long,
+        value_enum,
+        value_name = "MODE",
+        conflicts_with_all = &["actually-delete", "server-dry-run"],
+        env = "SHOPVAC_DRY_RUN"
+    )]
+    dry_run: Option<DryRun>,
+
     /// Namespace exlusion regex
-    #[clap(short, long, default_value = "(openshift.*)|(kube.*)")]
+    #[clap(
+        short,
+        long,
+        default_value = "(openshift.*)|(kube.*)",
+        env = "SHOPVAC_EXCLUDE_NAMESPACE_PATTERN"
+    )]
     exclude_namespace_pattern: String,
+
+    /// Also consider pods in protected system namespaces (kube-system,
+    /// kube-public, kube-node-lease and openshift-*)
+    #[clap(long, env = "SHOPVAC_ALLOW_SYSTEM_NAMESPACES")]
+    allow_system_namespaces: bool,
+
+    /// Only remove pods in one of these phases (repeatable or comma separated)
+    #[clap(
+        long,
+        value_enum,
+        ignore_case = true,
+        value_delimiter = ',',
+        env = "SHOPVAC_PHASE"
+    )]
+    phase: Vec<PodPhase>,
+
+    /// Only remove pods whose `status.reason` is one of these, e.g. Evicted
+    /// (repeatable). These are removed regardless of age, --older-than and
+    /// --completed-for don't apply
+    #[clap(
+        long,
+        alias = "pod-status-reason",
+        value_name = "REASON",
+        env = "SHOPVAC_REASON"
+    )]
+    reason: Vec<String>,
+
+    /// Only remove pods where at least one container image matches this regex
+    #[clap(long, env = "SHOPVAC_IMAGE_PATTERN")]
+    image_pattern: Option<String>,
+
+    /// Only remove pods whose owners no longer exist, e.g. pods of a
+    /// ReplicaSet deleted without cascading. Looks up every owner, which
+    /// needs `get` on the owner kinds. Pods without one are left alone
+    #[clap(long, alias = "owner-generation-mismatch", env = "SHOPVAC_ORPHANED")]
+    orphaned: bool,
+
+    /// Never remove pods with an Event newer than this, e.g. 30m, they're
+    /// probably still starting up or being debugged
+    #[clap(long, value_name = "DURATION", env = "SHOPVAC_SKIP_RECENTLY_ACTIVE")]
+    skip_recently_active: Option<Interval>,
+
+    /// Never remove pods that are Running and Ready and started longer ago
+    /// than this, e.g. 30d, whatever their age. Keeps long lived healthy
+    /// workloads out of reach of the age filter
+    #[clap(
+        long,
+        value_name = "DURATION",
+        env = "SHOPVAC_EXCLUDE_RUNNING_LONGER_THAN"
+    )]
+    exclude_running_longer_than: Option<Interval>,
+
+    /// Never remove pods whose name matches this regex
+    #[clap(long, env = "SHOPVAC_EXCLUDE_POD_PATTERN")]
+    exclude_pod_pattern: Option<String>,
+
+    /// Only remove pods owned by one of these kinds, e.g. Job or Workflow
+    /// (repeatable or comma separated). Pods without an owner never match
+    #[clap(
+        long,
+        value_name = "KIND",
+        value_delimiter = ',',
+        env = "SHOPVAC_OWNER_KIND"
+    )]
+    owner_kind: Vec<String>,
+
+    /// Only remove pods without any owner, e.g. ones started with `kubectl
+    /// run` or by a tool that doesn't set owner references. Pods with an
+    /// owner are considered by default, this narrows that down
+    #[clap(
+        long,
+        conflicts_with_all = &["owner-kind", "orphaned"],
+        env = "SHOPVAC_NAKED_ONLY"
+    )]
+    naked_only: bool,
+
+    /// Log why every listed pod was selected or skipped, e.g. "skipped:
+    /// phase Running not in [Failed]", to debug filters that don't behave
+    #[clap(long, env = "SHOPVAC_EXPLAIN")]
+    explain: bool,
+
+    /// Never remove pods carrying this annotation, unless its value is "false"
+    #[clap(long, env = "SHOPVAC_PROTECTION_ANNOTATION")]
+    protection_annotation: Option<String>,
+
+    /// Only remove pods with this annotation, as `key=value` or just `key`
+    /// to match any value (repeatable)
+    #[clap(long, env = "SHOPVAC_ANNOTATION_SELECTOR")]
+    annotation_selector: Vec<AnnotationSelector>,
+
+    /// Only act on a random PERCENT of the matched pods, for canary cleanups
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        env = "SHOPVAC_SAMPLE"
+    )]
+    sample: Option<u8>,
+
+    /// Abort without deleting anything if more than this many pods match,
+    /// which usually means the selector is wrong
+    #[clap(long, value_name = "N", env = "SHOPVAC_MAX_PODS")]
+    max_pods: Option<usize>,
+
+    /// Seconds each pod gets to shut down, 0 deletes immediately. Defaults
+    /// to the pod's own terminationGracePeriodSeconds
+    #[clap(long, value_name = "SECONDS", env = "SHOPVAC_GRACE_PERIOD")]
+    grace_period: Option<u32>,
+
+    /// How dependents of deleted objects are garbage collected
+    #[clap(long, value_enum, env = "SHOPVAC_PROPAGATION_POLICY")]
+    propagation_policy: Option<Propagation>,
+
+    /// Log the exact delete parameters once before a dry run, to check
+    /// --grace-period and --propagation-policy before deleting for real
+    #[clap(long, env = "SHOPVAC_VERBOSE_DRY_RUN")]
+    verbose_dry_run: bool,
+
+    /// Start at most this many deletions per second
+    #[clap(
+        long,
+        value_name = "PER_SECOND",
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "SHOPVAC_RATE"
+    )]
+    rate: Option<u32>,
+
+    /// Delete in batches of this many, pausing --batch-delay between them to
+    /// watch how the cluster takes it. Each batch finishes before the pause,
+    /// and progress is logged at every boundary, so a run can be stopped
+    /// between batches
+    #[clap(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "SHOPVAC_BATCH_SIZE"
+    )]
+    batch_size: Option<u32>,
+
+    /// Pause between --batch-size batches, e.g. 30s or 5m
+    #[clap(
+        long,
+        default_value = "30s",
+        requires = "batch-size",
+        env = "SHOPVAC_BATCH_DELAY"
+    )]
+    batch_delay: Interval,
+
+    /// Keep running, repeating the cleanup every --interval until SIGTERM or
+    /// Ctrl-C. A signal lets the cycle in flight finish first.
+    #[clap(long, env = "SHOPVAC_WATCH")]
+    watch: bool,
+
+    /// Time between --watch cycles, e.g. 90s, 10m or 1h
+    #[clap(
+        long,
+        default_value = "10m",
+        requires = "watch",
+        env = "SHOPVAC_INTERVAL"
+    )]
+    interval: Interval,
+
+    /// Pods fetched per list call, smaller pages keep large clusters from
+    /// timing out the list
+    #[clap(
+        long,
+        default_value_t = 500,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "SHOPVAC_CHUNK_SIZE"
+    )]
+    chunk_size: u32,
+
+    /// Order in which matched pods are deleted [default: age]
+    ///
+    /// Implies --plan, streamed deletes go in list order.
+    #[clap(long, value_enum, env = "SHOPVAC_DELETE_ORDER")]
+    delete_order: Option<DeleteOrder>,
+
+    /// Scan everything before deleting anything. Otherwise pods are deleted
+    /// as the scan finds them, unless --max-pods, --sample, --delete-order,
+    /// --output table or json, --diff-against, --orphaned,
+    /// --skip-recently-active, --namespace-from-file or the all-namespaces
+    /// prompt need the whole match set first
+    ///
+    /// Streamed cluster wide scans also need permission to list pods in
+    /// every namespace, with --plan namespaces that forbid it are skipped.
+    #[clap(long, env = "SHOPVAC_PLAN")]
+    plan: bool,
+
+    /// Only print the number of matching pods to stdout and exit, for
+    /// monitoring. Logs go to stderr and default to warnings only
+    #[clap(
+        long,
+        conflicts_with_all = &["actually-delete", "server-dry-run", "dry-run", "watch"],
+        env = "SHOPVAC_COUNT_ONLY"
+    )]
+    count_only: bool,
+
+    /// How to present the matched pods
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Log,
+        env = "SHOPVAC_OUTPUT"
+    )]
+    output: OutputFormat,
+
+    /// Compare the matched pods against the --output json of an earlier run
+    /// and print the pods newly matched and no longer matched
+    #[clap(long, env = "SHOPVAC_DIFF_AGAINST")]
+    diff_against: Option<PathBuf>,
+
+    /// Append a JSON line per deletion attempt to this file
+    #[clap(long, env = "SHOPVAC_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
+
+    /// Save the container logs of each pod to this directory right before
+    /// deleting it
+    #[clap(long, value_name = "DIR", env = "SHOPVAC_ARCHIVE_LOGS")]
+    archive_logs: Option<PathBuf>,
+
+    /// Annotate each pod with why it was selected
+    /// (`shopvac.io/deleted-by-policy`) right before deleting it, so audit
+    /// logs and webhooks watching pod changes record the reason. Costs an
+    /// extra patch per pod
+    #[clap(long, env = "SHOPVAC_AUDIT_ANNOTATE")]
+    audit_annotate: bool,
+
+    /// Remember when the last deleting run started in this file, and only
+    /// consider pods created since then minus the age window. Runs with
+    /// failed deletes don't move the marker, so those pods are retried
+    ///
+    /// Pods an earlier run left alone because of another filter stay out of
+    /// scope, a run without --state-file picks them up again.
+    #[clap(long, conflicts_with = "completed-for", env = "SHOPVAC_STATE_FILE")]
+    state_file: Option<PathBuf>,
+
+    /// Write a JSON summary of the run into this ConfigMap when finished
+    #[clap(long, env = "SHOPVAC_REPORT_CONFIGMAP")]
+    report_configmap: Option<String>,
+
+    /// POST a JSON summary of the run to this webhook (Slack compatible)
+    #[clap(long, alias = "notify-url", env = "SHOPVAC_NOTIFY_WEBHOOK")]
+    notify_webhook: Option<String>,
+
+    /// When to send the webhook notification
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = NotifyOn::Threshold,
+        env = "SHOPVAC_NOTIFY_ON"
+    )]
+    notify_on: NotifyOn,
+
+    /// Number of deletions (or failures) a run must exceed to notify
+    #[clap(long, default_value_t = 0, env = "SHOPVAC_NOTIFY_THRESHOLD")]
+    notify_threshold: usize,
+
+    /// Push run metrics to this Prometheus pushgateway
+    ///
+    /// Exposes the gauges `shopvac_pods_matched`, `shopvac_pods_deleted` and
+    /// `shopvac_pods_failed` (labelled by `namespace` and `dry_run`), plus
+    /// `shopvac_run_duration_seconds` (labelled by `dry_run`).
+    #[clap(long, env = "SHOPVAC_METRICS_PUSHGATEWAY")]
+    metrics_pushgateway: Option<String>,
+
+    /// Write run metrics to this file in the node-exporter textfile format
+    ///
+    /// Uses the same metric names as --metrics-pushgateway.
+    #[clap(long, env = "SHOPVAC_METRICS_TEXTFILE")]
+    metrics_textfile: Option<PathBuf>,
+
+    /// Name of the PodCleaner that owns the report ConfigMap
+    #[clap(
+        long,
+        hide = true,
+        requires = "report-owner-uid",
+        env = "SHOPVAC_REPORT_OWNER_NAME"
+    )]
+    report_owner_name: Option<String>,
+
+    /// UID of the PodCleaner that owns the report ConfigMap
+    #[clap(
+        long,
+        hide = true,
+        requires = "report-owner-name",
+        env = "SHOPVAC_REPORT_OWNER_UID"
+    )]
+    report_owner_uid: Option<String>,
+}
+
+impl Args {
+    /// The effective age threshold, --older-than when given and
+    /// --older-than-hours otherwise
+    fn older_than_hours(&self) -> i64 {
+        match self.older_than {
+            Some(days) => i64::from(days) * 24,
+            None => i64::from(self.older_than_hours),
+        }
+    }
+
+    fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            label_selector: self.label_selector.clone(),
+            not_labels: self.not_label.clone(),
+            field_selector: self.field_selector.clone(),
+            older_than: Duration::hours(self.older_than_hours()),
+            created_after: None,
+            completed_for: self
+                .completed_for
+                .map(|d| Duration::seconds(d.0.as_secs() as i64)),
+            exclude_namespace_pattern: self.exclude_namespace_pattern.clone(),
+            allow_system_namespaces: self.allow_system_namespaces,
+            annotation_selectors: self.annotation_selector.clone(),
+            phases: self.phase.clone(),
+            reasons: self.reason.clone(),
+            image_pattern: self.image_pattern.clone(),
+            exclude_pod_pattern: self.exclude_pod_pattern.clone(),
+            owner_kinds: self.owner_kind.clone(),
+            naked_only: self.naked_only,
+            protection_annotation: self.protection_annotation.clone(),
+            exclude_running_longer_than: self
+                .exclude_running_longer_than
+                .map(|d| Duration::seconds(d.0.as_secs() as i64)),
+            chunk_size: self.chunk_size,
+            explain: self.explain,
+        }
+    }
+
+    /// Whether matched pods are deleted while the scan is still running,
+    /// rather than scanned in full first
+    fn streams(&self) -> bool {
+        (self.actually_delete || self.server_dry_run)
+            && !self.plan
+            && self.resource == ResourceKind::Pod
+            && self.gvk.is_none()
+            && self.max_pods.is_none()
+            && self.sample.is_none()
+            && self.delete_order.is_none()
+            && self.output == OutputFormat::Log
+            && self.diff_against.is_none()
+            && !self.orphaned
+            && self.skip_recently_active.is_none()
+            && self.namespaces.is_empty()
+            && !(self.all_namespaces && self.actually_delete && !self.yes)
+    }
+
+    /// How deletes are issued, for every --resource
+    fn delete_options(&self) -> DeleteOptions {
+        DeleteOptions {
+            params: DeleteParams {
+                dry_run: self.server_dry_run,
+                grace_period_seconds: self.grace_period,
+                propagation_policy: self.propagation_policy.map(Propagation::policy),
+                ..DeleteParams::default()
+            },
+            rate: self.rate,
+            batch_size: self.batch_size.map(|size| size as usize),
+            batch_delay: self.batch_delay.0,
+            ..DeleteOptions::default()
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ResourceKind {
+    Pod,
+    /// PersistentVolumeClaims no running pod mounts anymore
+    Pvc,
+    /// ReplicaSets scaled to zero, e.g. the old revisions of Deployments.
+    /// Removes what `kubectl rollout undo` could go back to
+    #[clap(name = "replicaset")]
+    #[serde(rename = "replicaset")]
+    ReplicaSet,
+}
+
+impl ResourceKind {
+    /// The resource name as RBAC knows it
+    fn plural(&self) -> &'static str {
+        match self {
+            ResourceKind::Pod => "pods",
+            ResourceKind::Pvc => "persistentvolumeclaims",
+            ResourceKind::ReplicaSet => "replicasets",
+        }
+    }
+
+    /// The API group, `""` for the core one
+    fn group(&self) -> &'static str {
+        match self {
+            ResourceKind::Pod | ResourceKind::Pvc => "",
+            ResourceKind::ReplicaSet => "apps",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum DryRun {
+    /// List the matches without sending any deletes
+    Client,
+    /// Send the deletes with `dryRun=All`, so the apiserver and admission
+    /// webhooks validate them without removing anything
+    Server,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Propagation {
+    /// Leave the dependents behind
+    Orphan,
+    /// Delete the object right away and the dependents after it (the
+    /// apiserver default for pods)
+    Background,
+    /// Delete the dependents before the object
+    Foreground,
+}
+
+impl Propagation {
+    fn policy(self) -> PropagationPolicy {
+        match self {
+            Propagation::Orphan => PropagationPolicy::Orphan,
+            Propagation::Background => PropagationPolicy::Background,
+            Propagation::Foreground => PropagationPolicy::Foreground,
+        }
+    }
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum DeleteOrder {
+    /// Oldest pods first
+    Age,
+    /// Shuffled, to spread load across owning controllers and nodes
+    Random,
+    /// Alphabetically by namespace and name
+    Name,
+}
+
+/// Exits with the code of the failure, see [`shopvac::error::exit_code`], so
+/// scripts can tell missing permissions and timeouts from other errors.
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    if let Err(e) = try_main().await {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(shopvac::error::exit_code(e.chain()));
+    }
+}
+
+async fn try_main() -> Result<()> {
     color_eyre::install()?;
-    tracing_subscriber::fmt()
-        .with_max_level(LevelFilter::DEBUG)
-        .init();
 
-    let args = Args::parse();
+    let cli_matches = Cli::command().get_matches();
+    let Cli { command, args } = Cli::from_arg_matches(&cli_matches).unwrap_or_else(|e| e.exit());
+    // the matches of whichever (sub)command holds the args, so value sources
+    // can be checked when layering presets and the config file
+    let (mut args, matches) = match command {
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "shopvac",
+                &mut std::io::stdout(),
+            );
+            return Ok(());
+        }
+        Some(Command::Clean(args)) => (*args, cli_matches.subcommand_matches("clean")),
+        Some(Command::Scan(args)) => {
+            if args.actually_delete {
+                Cli::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        "`shopvac scan` never deletes, use `shopvac clean --actually-delete`",
+                    )
+                    .exit();
+            }
+            (*args, cli_matches.subcommand_matches("scan"))
+        }
+        None => (args, Some(&cli_matches)),
+    };
+    let matches = matches.expect("subcommand matches");
+    // presets fill in first so that both the config file and explicit flags
+    // can override them
+    if let Some(name) = args.preset {
+        match name.preset() {
+            Some(preset) => preset.apply(&mut args, matches),
+            None => {
+                for preset in PRESETS {
+                    println!("{}\n", preset.describe());
+                }
+                return Ok(());
+            }
+        }
+    }
+    if let Some(path) = &args.config {
+        Config::load(path)?.merge_into(&mut args, matches)?;
+    }
 
-    let client = Client::try_default().await?;
-    let pods: Api<Pod> = if let Some(ns) = &args.namespace {
+    // logging waits for the config file, which may set the verbosity
+    let level = if args.quiet {
+        Some(LevelFilter::WARN)
+    } else if args.verbose {
+        Some(LevelFilter::TRACE)
+    } else {
+        None
+    };
+    // --count-only keeps stdout for the count alone, and --output json for
+    // the document
+    let (writer, default_level) = if args.count_only {
+        (BoxMakeWriter::new(io::stderr), LevelFilter::WARN)
+    } else if args.output == OutputFormat::Json {
+        (BoxMakeWriter::new(io::stderr), LevelFilter::INFO)
+    } else {
+        (BoxMakeWriter::new(io::stdout), LevelFilter::INFO)
+    };
+    match (level, &args.log_level) {
+        (None, Some(directives)) => tracing_subscriber::fmt()
+            .with_writer(writer)
+            .with_env_filter(EnvFilter::try_new(directives)?)
+            .init(),
+        (level, _) => tracing_subscriber::fmt()
+            .with_writer(writer)
+            .with_max_level(level.unwrap_or(default_level))
+            .init(),
+    }
+    if cli_matches.subcommand_name().is_none() {
+        tracing::warn!(
+            "Running shopvac without a subcommand is deprecated and will be removed in the \
+             next release, use `shopvac clean` instead."
+        );
+    }
+
+    // checked after the config merge since the namespace may come from there
+    if args.namespace.is_none() && !args.all_namespaces && args.namespace_from_file.is_none() {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "either --namespace, --all-namespaces or --namespace-from-file is required, \
+                 cluster mode is no longer implied by leaving out --namespace",
+            )
+            .exit();
+    }
+    if let Some(path) = &args.namespace_from_file {
+        if args.namespace.is_some() || args.all_namespaces {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--namespace-from-file can't be combined with --namespace or \
+                     --all-namespaces",
+                )
+                .exit();
+        }
+        if args.resource != ResourceKind::Pod || args.gvk.is_some() {
+            bail!(ShopvacError::Validation(
+                "--namespace-from-file only applies to --resource pod".to_string()
+            ));
+        }
+        let list = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Unable to read namespace file {}", path.display()))?;
+        args.namespaces = shopvac::parse_namespace_list(&list);
+        if args.namespaces.is_empty() {
+            bail!(ShopvacError::Validation(format!(
+                "{} doesn't list any namespaces",
+                path.display()
+            )));
+        }
+    }
+    if args.dry_run == Some(DryRun::Server) {
+        args.server_dry_run = true;
+    }
+    // clap only sees the conflict when both come from flags or the environment
+    if args.actually_delete && (args.server_dry_run || args.dry_run.is_some()) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--dry-run and --server-dry-run can't be combined with --actually-delete",
+            )
+            .exit();
+    }
+    if args.count_only && (args.actually_delete || args.server_dry_run || args.watch) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--count-only never deletes and runs once, it can't be combined with \
+                 --actually-delete, --server-dry-run or --watch",
+            )
+            .exit();
+    }
+    if args.state_file.is_some() && args.completed_for.is_some() {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--state-file goes by creation time, it can't be combined with --completed-for",
+            )
+            .exit();
+    }
+    if args.gvk.is_some() && args.resource != ResourceKind::Pod {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--gvk picks the resource itself, it can't be combined with --resource",
+            )
+            .exit();
+    }
+    if args.naked_only && (!args.owner_kind.is_empty() || args.orphaned) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--naked-only selects pods without owners, it can't be combined with \
+                 --owner-kind or --orphaned",
+            )
+            .exit();
+    }
+
+    let (mut config, context) = kube_config(&args).await?;
+    // kube applies this to both connecting and reading, for every call
+    config.timeout = Some(args.request_timeout.0);
+    let mut target = format!("context {} ({})", context, config.cluster_url);
+    if let Some(user) = &args.as_user {
+        config.auth_info.impersonate = Some(user.clone());
+        if !args.as_group.is_empty() {
+            config.auth_info.impersonate_groups = Some(args.as_group.clone());
+        }
+        target = format!("{} as {}", target, user);
+    }
+    if args.actually_delete {
+        tracing::warn!(
+            "Targeting {}, {} WILL be deleted",
+            target,
+            match &args.gvk {
+                Some(gvk) => gvk.to_string(),
+                None => args.resource.plural().to_string(),
+            }
+        );
+    } else {
+        tracing::info!("Targeting {}", target);
+    }
+    let client = Client::try_from(config)?;
+
+    // an impersonated identity is usually deliberately narrow, and RBAC the
+    // controller just minted may not have settled, check it can do the job
+    // before we start rather than failing pod by pod
+    if args.health_check || args.as_user.is_some() {
+        let mut verbs = vec!["list"];
+        if args.actually_delete || args.server_dry_run {
+            verbs.push("delete");
+        }
+        let namespaces: Vec<Option<&str>> = match &args.namespace {
+            Some(ns) => vec![Some(ns)],
+            None if !args.namespaces.is_empty() => {
+                args.namespaces.iter().map(|ns| Some(ns.as_str())).collect()
+            }
+            None => vec![None],
+        };
+        // a --gvk resource is only known by its plural after discovery
+        let (group, resource) = match &args.gvk {
+            Some(gvk) => {
+                let discovered = shopvac::discover(client.clone(), gvk).await?;
+                (discovered.group, discovered.plural)
+            }
+            None => (
+                args.resource.group().to_string(),
+                args.resource.plural().to_string(),
+            ),
+        };
+        let who = match &args.as_user {
+            Some(user) => format!(" as user {}", user),
+            None => String::new(),
+        };
+        for ns in namespaces {
+            for verb in &verbs {
+                if !access::can_i(client.clone(), verb, &group, &resource, ns).await? {
+                    bail!(ShopvacError::NoPermission(format!(
+                        "cannot {} {} in {}{}",
+                        verb,
+                        resource,
+                        ns.unwrap_or("the cluster"),
+                        who
+                    )));
+                }
+            }
+        }
+        tracing::info!(
+            "Health check passed, may {} {}",
+            verbs.join(" and "),
+            resource
+        );
+    }
+    let (pods, reports): (Api<Pod>, Api<ConfigMap>) = if let Some(ns) = &args.namespace {
         tracing::info!("Initialized in namespace mode: {ns}", ns = ns);
-        Api::namespaced(client, ns)
+        (
+            Api::namespaced(client.clone(), ns),
+            Api::namespaced(client.clone(), ns),
+        )
+    } else if !args.namespaces.is_empty() {
+        tracing::info!(
+            "Initialized for {} namespaces listed in a file",
+            args.namespaces.len()
+        );
+        (
+            Api::all(client.clone()),
+            Api::default_namespaced(client.clone()),
+        )
     } else {
         tracing::warn!("Initialized in cluster mode!");
-        Api::all(client)
+        (
+            Api::all(client.clone()),
+            Api::default_namespaced(client.clone()),
+        )
     };
 
-    let mut lp = ListParams::default();
+    for ns in args.namespace.iter().chain(&args.namespaces) {
+        if is_system_namespace(ns) && !args.allow_system_namespaces {
+            bail!(ShopvacError::Validation(format!(
+                "{} is a protected system namespace, pass --allow-system-namespaces to clean it",
+                ns
+            )));
+        }
+    }
 
-    if let Some(ls) = args.label_selector {
-        lp = lp.labels(&ls)
+    if args.count_only {
+        return count(&args, client, &pods).await;
     }
-    if let Some(fs) = args.field_selector {
-        lp = lp.fields(&fs)
+    if args.verbose_dry_run && !args.actually_delete {
+        tracing::info!(
+            "Deletes {} sent with {}",
+            if args.server_dry_run {
+                "are"
+            } else {
+                "would be"
+            },
+            serde_json::to_string(&args.delete_options().params)?
+        );
+    }
+    if !args.watch {
+        return run(&args, client, &PodSource::List(pods), &reports, &target).await;
     }
 
-    // TODO: look at the 'predicates' library for this, can potentially compose
-    // to create multiple filters like allowlist, denylist, etc.
-    //  ex. https://docs.rs/predicates/latest/predicates/prelude/predicate/str/fn.is_match.html
-    let ns_regex: Regex = Regex::new(&args.exclude_namespace_pattern)?;
+    if args.all_namespaces && args.actually_delete && !args.yes {
+        bail!(ShopvacError::Validation(
+            "--watch can't prompt before every cycle, pass --yes to delete across all namespaces"
+                .to_string()
+        ));
+    }
+    // installed once up front, so a signal during a cycle lets that cycle
+    // finish and then ends the loop
+    let mut stop = tokio::spawn(shutdown_signal());
+    let mut source = PodSource::List(pods.clone());
+    loop {
+        if let Err(e) = run(&args, client.clone(), &source, &reports, &target).await {
+            tracing::error!("Cleanup cycle failed: {:?}", e);
+        }
+        // a reflector over a file's namespaces would have to watch the whole
+        // cluster, those keep listing namespace by namespace
+        if matches!(source, PodSource::List(_)) && args.namespaces.is_empty() {
+            // the first cycle lists, from then on the reflector is warm
+            source = PodSource::Cache(watch::cache(
+                pods.clone(),
+                args.scan_options().list_params(),
+                args.request_timeout.0,
+            ));
+        }
 
-    // use the pod API to grab all of the pods that meet our pre-filter criteria
-    let pod_list = pods.list(&lp).await?;
+        tracing::info!("Next cleanup in {:?}", args.interval.0);
+        tokio::select! {
+            _ = &mut stop => break,
+            _ = time::sleep(args.interval.0) => {}
+        }
+    }
+    tracing::info!("Stopped watching.");
 
-    // do some argument handling
-    let older_than_hours = if args.older_than * 24 <= args.older_than_hours {
-        args.older_than * 24
-    } else {
-        args.older_than_hours
+    Ok(())
+}
+
+/// `--count-only`: prints how many objects match and nothing else, skipping
+/// the plan, the reports and the per object logging.
+async fn count(args: &Args, client: Client, pods: &Api<Pod>) -> Result<()> {
+    let scan = args.scan_options();
+    if let Some(gvk) = &args.gvk {
+        let resource = shopvac::discover(client.clone(), gvk).await?;
+        let api = match &args.namespace {
+            Some(ns) => Api::namespaced_with(client, ns, &resource),
+            None => Api::all_with(client, &resource),
+        };
+        println!(
+            "{}",
+            shopvac::scan_dynamic(&api, &resource, &scan).await?.len()
+        );
+        return Ok(());
+    }
+    let mut targets = match args.resource {
+        ResourceKind::Pod if !args.namespaces.is_empty() => {
+            shopvac::scan_namespaces(client.clone(), &args.namespaces, &scan)
+                .await?
+                .targets
+        }
+        ResourceKind::Pod if args.namespace.is_none() => {
+            shopvac::scan_cluster(client.clone(), &scan).await?.targets
+        }
+        ResourceKind::Pod => shopvac::scan_namespace(pods, &scan).await?.targets,
+        ResourceKind::Pvc => {
+            let pvcs = match &args.namespace {
+                Some(ns) => Api::namespaced(client, ns),
+                None => Api::all(client),
+            };
+            println!("{}", shopvac::scan_pvcs(&pvcs, pods, &scan).await?.len());
+            return Ok(());
+        }
+        ResourceKind::ReplicaSet => {
+            let replicasets = match &args.namespace {
+                Some(ns) => Api::namespaced(client, ns),
+                None => Api::all(client),
+            };
+            println!(
+                "{}",
+                shopvac::scan_replicasets(&replicasets, pods, &scan)
+                    .await?
+                    .len()
+            );
+            return Ok(());
+        }
     };
+    if args.orphaned {
+        targets = shopvac::retain_orphans(client.clone(), targets).await?;
+    }
+    if let Some(within) = args.skip_recently_active {
+        targets = shopvac::skip_recently_active(
+            client,
+            targets,
+            Duration::seconds(within.0.as_secs() as i64),
+        )
+        .await?;
+    }
+    println!("{}", targets.len());
+    Ok(())
+}
 
-    let bad_pods: Vec<String> = pod_list
-        .iter()
-        .filter(|p| {
-            let ns = p.metadata.namespace.as_ref().unwrap();
-            !ns_regex.is_match(ns)
-        })
-        .filter_map(move |p| {
-            let now = offset::Utc::now();
-
-            if let Some(ct) = &p.metadata.creation_timestamp {
-                let duration = now - ct.0;
-                if duration.num_hours() > (older_than_hours as i64) {
-                    tracing::info!(
-                        "Found bad pod! {}:{}, duration: {:?} hours old",
-                        p.namespace().as_ref().unwrap(),
-                        p.name(),
-                        duration.num_hours()
-                    );
-                    Some(p.name())
-                } else {
-                    None
-                }
-            } else {
-                None
+/// One cleanup cycle: scan, delete (or not), then report on it.
+async fn run(
+    args: &Args,
+    client: Client,
+    pods: &PodSource,
+    reports: &Api<ConfigMap>,
+    target: &str,
+) -> Result<()> {
+    let started = Instant::now();
+    let started_at = offset::Utc::now();
+    let older_than_hours = args.older_than_hours();
+    let mut scan = args.scan_options();
+    if let Some(path) = &args.state_file {
+        if let Some(last_run) = state::read(path)? {
+            // anything created before this was already old enough last time
+            scan.created_after = Some(last_run - scan.older_than);
+            tracing::info!(
+                "Only considering pods created since {}",
+                last_run - scan.older_than
+            );
+        }
+    }
+    let filters = Filters {
+        namespace: args.namespace.clone(),
+        // including the negated --not-label selectors
+        label_selector: scan.list_params().label_selector,
+        field_selector: args.field_selector.clone(),
+        older_than_hours,
+        completed_for_seconds: args.completed_for.map(|d| d.0.as_secs()),
+        exclude_namespace_pattern: args.exclude_namespace_pattern.clone(),
+        annotation_selectors: args
+            .annotation_selector
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        phases: args.phase.iter().map(|p| p.as_str().to_string()).collect(),
+        reasons: args.reason.clone(),
+        protection_annotation: args.protection_annotation.clone(),
+        image_pattern: args.image_pattern.clone(),
+        orphaned: args.orphaned,
+        skip_recently_active_seconds: args.skip_recently_active.map(|d| d.0.as_secs()),
+        exclude_running_longer_than_seconds: args
+            .exclude_running_longer_than
+            .map(|d| d.0.as_secs()),
+        exclude_pod_pattern: args.exclude_pod_pattern.clone(),
+        owner_kinds: args.owner_kind.clone(),
+        naked_only: args.naked_only,
+    };
+    if let (Some(dir), true) = (&args.archive_logs, args.actually_delete) {
+        logs::prepare(dir)?;
+    }
+    let mut audit = match &args.audit_log {
+        Some(path) => Some(AuditLog::open(
+            path,
+            filters.clone(),
+            !args.actually_delete,
+        )?),
+        None => None,
+    };
+    let mut summary = RunSummary::new(filters, !args.actually_delete);
+
+    if let Some(gvk) = &args.gvk {
+        dynamic::clean(args, client, gvk, &scan, target, &mut summary, &mut audit).await?
+    } else {
+        match args.resource {
+            ResourceKind::Pod if args.streams() => {
+                stream_pods(args, client, pods, &scan, &mut summary, &mut audit).await?
+            }
+            ResourceKind::Pod => {
+                clean_pods(args, client, pods, &scan, target, &mut summary, &mut audit).await?
+            }
+            ResourceKind::Pvc => {
+                pvc::clean(args, client, &scan, target, &mut summary, &mut audit).await?
+            }
+            ResourceKind::ReplicaSet => {
+                replicaset::clean(args, client, &scan, target, &mut summary, &mut audit).await?
             }
-        })
-        .collect();
+        }
+    }
+
+    if let Some(audit) = audit.as_mut() {
+        audit.flush()?;
+    }
+
+    if args.namespace.is_none() && !summary.namespaces.is_empty() {
+        summary.log_namespace_breakdown();
+    }
+
+    if args.metrics_pushgateway.is_some() || args.metrics_textfile.is_some() {
+        let rendered = metrics::render(&summary, started.elapsed());
+        if let Some(path) = &args.metrics_textfile {
+            if let Err(e) = metrics::write_textfile(path, &rendered) {
+                tracing::error!("{:?}", e);
+            }
+        }
+        if let Some(url) = &args.metrics_pushgateway {
+            if let Err(e) = metrics::push(url, rendered).await {
+                tracing::error!("{:?}", e);
+            }
+        }
+    }
+
+    if let Some(name) = &args.report_configmap {
+        let owner = args
+            .report_owner_name
+            .clone()
+            .zip(args.report_owner_uid.clone())
+            .map(|(name, uid)| OwnerReference {
+                api_version: "shopvac.io/v1".to_string(),
+                kind: "PodCleaner".to_string(),
+                name,
+                uid,
+                ..OwnerReference::default()
+            });
+        report::write_configmap(reports, name, owner, &summary).await?;
+        tracing::info!("Wrote run summary to ConfigMap {}", name);
+    }
+
+    if let Some(url) = &args.notify_webhook {
+        if notify::should_notify(args.notify_on, args.notify_threshold, &summary) {
+            notify::send(url, &summary).await;
+        }
+    }
+
+    if args.actually_delete && (summary.skipped > 0 || summary.interrupted) {
+        bail!("Interrupted before all deletions were issued");
+    }
+
+    if let (Some(path), true) = (&args.state_file, args.actually_delete) {
+        if summary.failed == 0 {
+            state::write(path, started_at)?;
+        } else {
+            tracing::warn!(
+                "{} deletes failed, not moving the marker in {}",
+                summary.failed,
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Scans for matching pods and deletes them, or records them as a dry run.
+async fn clean_pods(
+    args: &Args,
+    client: Client,
+    pods: &PodSource,
+    scan: &ScanOptions,
+    target: &str,
+    summary: &mut RunSummary,
+    audit: &mut Option<AuditLog>,
+) -> Result<()> {
+    // use the pod API to grab all of the pods that meet our pre-filter criteria
+    let found = match pods {
+        PodSource::List(_) if !args.namespaces.is_empty() => {
+            shopvac::scan_namespaces(client.clone(), &args.namespaces, scan).await?
+        }
+        PodSource::List(_) if args.namespace.is_none() => {
+            shopvac::scan_cluster(client.clone(), scan).await?
+        }
+        PodSource::List(api) => shopvac::scan_namespace(api, scan).await?,
+        PodSource::Cache(store) => {
+            let cached = store.state();
+            ScanReport {
+                scanned: cached.len(),
+                targets: shopvac::select_pods(cached, scan)?,
+                forbidden: Vec::new(),
+            }
+        }
+    };
+    summary.scanned = Some(found.scanned);
+    let forbidden = found.forbidden;
+    let mut bad_pods = found.targets;
+    if args.orphaned {
+        let matched = bad_pods.len();
+        bad_pods = shopvac::retain_orphans(client.clone(), bad_pods).await?;
+        tracing::info!(
+            "{} of {} matched pods have lost their owners.",
+            bad_pods.len(),
+            matched
+        );
+    }
+    if let Some(within) = args.skip_recently_active {
+        bad_pods = shopvac::skip_recently_active(
+            client.clone(),
+            bad_pods,
+            Duration::seconds(within.0.as_secs() as i64),
+        )
+        .await?;
+    }
+
+    // metadata only scans leave out the pod specs the requests are read from
+    let requests_known = !scan.metadata_only();
+    let output = args.output;
+    // the table lists these already, keep the logs quiet
+    if output == OutputFormat::Log {
+        let now = offset::Utc::now();
+        for t in &bad_pods {
+            log_found(t, now);
+        }
+    }
 
     tracing::info!("Total of {} pods to delete found.", bad_pods.len());
-    // streaming delete, buffered 10 at a time as to not overwhelm
-    // the kubeapi server
-    //
-    // note: this will return instantly, it does not wait for finalizers!
-    if args.actually_delete {
-        tracing::info!("Starting deletions...");
+    if let Some(max) = args.max_pods {
+        if bad_pods.len() > max {
+            tracing::error!(
+                "Matched {} pods, more than --max-pods {}, refusing to continue.",
+                bad_pods.len(),
+                max
+            );
+            bail!("Matched pod count exceeds --max-pods, check the selectors");
+        }
+    }
+    if let Some(percent) = args.sample {
+        let matched = bad_pods.len();
+        // round up so a small match set still samples at least one pod
+        let keep = (matched * percent as usize + 99) / 100;
+        bad_pods.shuffle(&mut rand::thread_rng());
+        bad_pods.truncate(keep);
+        tracing::info!(
+            "Sampled {} of {} matched pods ({}%).",
+            keep,
+            matched,
+            percent
+        );
+    }
+    match args.delete_order.unwrap_or(DeleteOrder::Age) {
+        DeleteOrder::Age => bad_pods.sort_by_key(|t| t.pod.metadata.creation_timestamp.clone()),
+        DeleteOrder::Random => bad_pods.shuffle(&mut rand::thread_rng()),
+        DeleteOrder::Name => bad_pods.sort_by_key(|t| (t.namespace(), t.name())),
+    }
 
-        let dp = &DeleteParams::default();
-        let pods = &pods;
+    match output {
+        OutputFormat::Log => {}
+        OutputFormat::Table => output::print_table(&bad_pods),
+        OutputFormat::Json => output::print_json(&bad_pods)?,
+    }
+    if let Some(previous) = &args.diff_against {
+        output::print_diff(previous, &bad_pods, output == OutputFormat::Json)?;
+    }
 
-        let _res = stream::iter(&bad_pods)
-            .map(|name: &String| async {
-                tracing::debug!("Deleting pod: {name}", name = name.clone());
-                pods.delete(name, dp).await
-            })
-            .buffer_unordered(10)
-            .collect::<Vec<_>>()
-            .await;
+    if args.all_namespaces && args.actually_delete && !args.yes && !bad_pods.is_empty() {
+        confirm(&format!(
+            "About to delete {} pods across all namespaces in {}.",
+            bad_pods.len(),
+            target
+        ))?;
+    }
+
+    if args.actually_delete || args.server_dry_run {
+        if args.server_dry_run {
+            tracing::info!("Starting server side dry run deletions...");
+        } else {
+            tracing::info!("Starting deletions...");
+        }
+
+        if let (Some(dir), true) = (&args.archive_logs, args.actually_delete) {
+            tracing::info!(
+                "Archiving logs of {} pods to {}",
+                bad_pods.len(),
+                dir.display()
+            );
+            stream::iter(&bad_pods)
+                .for_each_concurrent(args.delete_options().concurrency, |t| {
+                    logs::archive(client.clone(), dir, t)
+                })
+                .await;
+        }
+        if args.audit_annotate {
+            tracing::info!(
+                "Annotating {} pods with the deletion reason",
+                bad_pods.len()
+            );
+            stream::iter(&bad_pods)
+                .for_each_concurrent(args.delete_options().concurrency, |t| {
+                    audit::annotate(client.clone(), t, args.server_dry_run)
+                })
+                .await;
+        }
+
+        // stop pulling new pods off the list once we are asked to shut down,
+        // the in-flight deletes are still driven to completion
+        let report = shopvac::delete_pods(
+            client,
+            &bad_pods,
+            args.delete_options(),
+            stop_deleting(args.watch),
+        )
+        .await;
+        for (t, outcome) in &report.outcomes {
+            summary.record(&t.pod, outcome);
+            if let Some(audit) = audit.as_mut() {
+                audit.record(&t.pod, outcome)?;
+            }
+        }
+
+        let reclaimed: Requests = report
+            .outcomes
+            .iter()
+            .filter(|(_, o)| o.is_deleted())
+            .map(|(t, _)| Requests::of(&t.pod))
+            .sum();
+        log_deletes(args, summary, requests_known.then_some(reclaimed));
+
+        let slowest = report.slowest(5);
+        if !slowest.is_empty() {
+            tracing::info!("Deletes took {:?} in total, slowest:", report.elapsed);
+            for (t, took) in slowest {
+                tracing::info!("  {}/{}: {}ms", t.namespace(), t.name(), took.as_millis());
+            }
+        }
     } else {
+        let outcome = Outcome::Skipped {
+            reason: "dry run".to_string(),
+        };
+        for t in &bad_pods {
+            summary.record(&t.pod, &outcome);
+            if let Some(audit) = audit.as_mut() {
+                audit.record(&t.pod, &outcome)?;
+            }
+        }
+        if requests_known {
+            let reclaimable: Requests = bad_pods.iter().map(|t| Requests::of(&t.pod)).sum();
+            tracing::info!("Would reclaim {} of requests.", reclaimable);
+        }
         tracing::info!("Dry run initiated! Nothing was deleted.")
     }
 
+    if !forbidden.is_empty() {
+        tracing::warn!(
+            "Skipped {} namespaces without permission to list pods: {}",
+            forbidden.len(),
+            forbidden.join(", ")
+        );
+    }
+
     Ok(())
 }
+
+/// Deletes matches as the scan lists them instead of collecting them first,
+/// used when nothing needs the whole match set up front (see --plan).
+async fn stream_pods(
+    args: &Args,
+    client: Client,
+    pods: &PodSource,
+    scan: &ScanOptions,
+    summary: &mut RunSummary,
+    audit: &mut Option<AuditLog>,
+) -> Result<()> {
+    // a scan error ends the stream, the deletes already issued still finish
+    let scan_error = RefCell::new(None);
+    let now = offset::Utc::now();
+    let targets = match pods {
+        PodSource::List(api) => shopvac::scan_stream(api.clone(), scan)?
+            .scan((), |_, res| {
+                future::ready(match res {
+                    Ok(t) => {
+                        log_found(&t, now);
+                        Some(t)
+                    }
+                    Err(e) => {
+                        *scan_error.borrow_mut() = Some(e);
+                        None
+                    }
+                })
+            })
+            .boxed_local(),
+        PodSource::Cache(store) => {
+            stream::iter(shopvac::select_pods(store.state(), scan)?).boxed_local()
+        }
+    };
+
+    let targets = match (&args.archive_logs, args.actually_delete) {
+        (Some(dir), true) => {
+            let client = client.clone();
+            targets
+                .then(move |t| {
+                    let client = client.clone();
+                    async move {
+                        logs::archive(client, dir, &t).await;
+                        t
+                    }
+                })
+                .boxed_local()
+        }
+        _ => targets,
+    };
+    let targets = if args.audit_annotate {
+        let client = client.clone();
+        let dry_run = args.server_dry_run;
+        targets
+            .then(move |t| {
+                let client = client.clone();
+                async move {
+                    audit::annotate(client, &t, dry_run).await;
+                    t
+                }
+            })
+            .boxed_local()
+    } else {
+        targets
+    };
+
+    let interrupted = Cell::new(false);
+    let shutdown = async {
+        stop_deleting(args.watch).await;
+        interrupted.set(true);
+    };
+    if args.server_dry_run {
+        tracing::info!("Starting server side dry run deletions as pods are found...");
+    } else {
+        tracing::info!("Starting deletions as pods are found...");
+    }
+    let deletions = shopvac::delete_stream(client, targets, args.delete_options(), shutdown);
+    futures::pin_mut!(deletions);
+
+    let mut reclaimed = Requests::default();
+    while let Some((t, outcome)) = deletions.next().await {
+        if outcome.is_deleted() {
+            reclaimed = reclaimed + Requests::of(&t.pod);
+        }
+        summary.record(&t.pod, &outcome);
+        if let Some(audit) = audit.as_mut() {
+            audit.record(&t.pod, &outcome)?;
+        }
+    }
+    summary.interrupted = interrupted.get();
+
+    log_deletes(args, summary, (!scan.metadata_only()).then_some(reclaimed));
+    if let Some(e) = scan_error.borrow_mut().take() {
+        return Err(e).wrap_err("Scan failed part way through, the deletes already issued stand");
+    }
+    Ok(())
+}
+
+fn log_found(t: &PodTarget, now: DateTime<Utc>) {
+    let age = t
+        .pod
+        .metadata
+        .creation_timestamp
+        .as_ref()
+        .map_or(0, |ct| (now - ct.0).num_hours());
+    tracing::info!(
+        "Found bad pod! {}:{}, duration: {:?} hours old",
+        t.namespace(),
+        t.name(),
+        age
+    );
+}
+
+/// Logs the tally once deletes (or server side dry runs) are done.
+/// `reclaimed` is `None` when the scan left out the pod specs.
+fn log_deletes(args: &Args, summary: &RunSummary, reclaimed: Option<Requests>) {
+    if args.server_dry_run {
+        tracing::info!(
+            "Server dry run: {} pods would be deleted, {} would be forbidden, {} would fail otherwise, {} not attempted.",
+            summary.deleted,
+            summary.forbidden,
+            summary.failed - summary.forbidden,
+            summary.skipped
+        );
+        if let Some(reclaimed) = reclaimed {
+            tracing::info!("Would reclaim {} of requests.", reclaimed);
+        }
+    } else {
+        tracing::info!(
+            "Deleted {} pods ({} still terminating), {} were already gone, {} failed, {} not attempted.",
+            summary.deleted,
+            summary.terminating,
+            summary.already_gone,
+            summary.failed,
+            summary.skipped
+        );
+        if let Some(reclaimed) = reclaimed {
+            tracing::info!("Reclaimed {} of requests.", reclaimed);
+        }
+    }
+}
+
+/// Loads the kube config from an explicit kubeconfig and/or context, falling
+/// back to the usual in-cluster and `KUBECONFIG` inference when neither is set.
+///
+/// Also returns the name of the context in use, so it can be shown before
+/// anything gets deleted.
+async fn kube_config(args: &Args) -> Result<(kube::Config, String)> {
+    let options = KubeConfigOptions {
+        context: args.context.clone(),
+        ..KubeConfigOptions::default()
+    };
+    let kubeconfig = match &args.kubeconfig {
+        Some(path) => Some(Kubeconfig::read_from(path)?),
+        None => Kubeconfig::read().ok(),
+    };
+
+    match kubeconfig {
+        Some(kubeconfig) => {
+            let context = args
+                .context
+                .clone()
+                .or_else(|| kubeconfig.current_context.clone())
+                .unwrap_or_default();
+            let config = kube::Config::from_custom_kubeconfig(kubeconfig, &options).await?;
+            Ok((config, context))
+        }
+        None if args.context.is_some() => {
+            bail!("--context was given but no kubeconfig could be found")
+        }
+        None => Ok((kube::Config::infer().await?, "in-cluster".to_string())),
+    }
+}
+
+/// Asks for an explicit `y` on stdin, refusing outright when there is no
+/// terminal to ask on.
+fn confirm(message: &str) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        bail!(
+            "{} Refusing to continue without a terminal, pass --yes to confirm.",
+            message
+        );
+    }
+
+    print!("{} Continue? [y/N] ", message);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        bail!("Aborted, nothing was deleted.");
+    }
+    Ok(())
+}
+
+/// Resolves once no more deletes should be started. In --watch mode the cycle
+/// always runs to completion, and the signal ends the loop instead.
+async fn stop_deleting(watch: bool) {
+    if watch {
+        future::pending::<()>().await
+    } else {
+        shutdown_signal().await
+    }
+}
+
+/// Resolves once the process receives either SIGINT or SIGTERM, the latter
+/// being what a CronJob pod gets when it is cancelled.
+async fn shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => tracing::warn!("Received SIGINT, finishing in-flight deletions"),
+        _ = sigterm.recv() => tracing::warn!("Received SIGTERM, finishing in-flight deletions"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueSource;
+
+    use crate::testing::{parse, parse_with_env};
+
+    #[test]
+    fn bool_flags_are_read_from_the_env() {
+        for value in ["true", "1"] {
+            let (args, _) = parse_with_env(&[("SHOPVAC_ACTUALLY_DELETE", value)], &[]);
+            assert!(args.actually_delete, "{}", value);
+        }
+        for value in ["false", "0"] {
+            let (args, _) = parse_with_env(&[("SHOPVAC_ACTUALLY_DELETE", value)], &[]);
+            assert!(!args.actually_delete, "{}", value);
+        }
+        let (args, _) = parse(&[]);
+        assert!(!args.actually_delete);
+    }
+
+    #[test]
+    fn flags_beat_the_env() {
+        let (args, matches) = parse_with_env(
+            &[("SHOPVAC_ACTUALLY_DELETE", "false")],
+            &["--actually-delete"],
+        );
+        assert!(args.actually_delete);
+        assert_eq!(
+            matches.value_source("actually-delete"),
+            Some(ValueSource::CommandLine)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Prometheus exposition of a run summary, either pushed to a pushgateway or
+//! written out for the node-exporter textfile collector.
+use std::{fmt::Write as _, fs, path::Path, time::Duration};
+
+use color_eyre::eyre::{Result, WrapErr};
+
+use crate::report::{NamespaceCounts, RunSummary};
+
+/// Renders the summary in the Prometheus text exposition format.
+pub fn render(summary: &RunSummary, duration: Duration) -> String {
+    let counters: [(&str, &str, fn(&NamespaceCounts) -> usize); 3] = [
+        (
+            "shopvac_pods_matched",
+            "Pods that matched the cleanup filters.",
+            |c| c.matched,
+        ),
+        (
+            "shopvac_pods_deleted",
+            "Pods that were successfully deleted.",
+            |c| c.deleted,
+        ),
+        (
+            "shopvac_pods_failed",
+            "Pods whose deletion request failed.",
+            |c| c.failed,
+        ),
+    ];
+
+    let mut out = String::new();
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for (ns, counts) in &summary.namespaces {
+            let _ = writeln!(
+                out,
+                "{}{{namespace=\"{}\",dry_run=\"{}\"}} {}",
+                name,
+                ns,
+                summary.dry_run,
+                value(counts)
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP shopvac_run_duration_seconds Wall clock duration of the run."
+    );
+    let _ = writeln!(out, "# TYPE shopvac_run_duration_seconds gauge");
+    let _ = writeln!(
+        out,
+        "shopvac_run_duration_seconds{{dry_run=\"{}\"}} {}",
+        summary.dry_run,
+        duration.as_secs_f64()
+    );
+    out
+}
+
+/// Writes the metrics via a temporary file and rename so the textfile
+/// collector never scrapes a half written file.
+pub fn write_textfile(path: &Path, metrics: &str) -> Result<()> {
+    let tmp = path.with_extension("prom.tmp");
+    fs::write(&tmp, metrics)
+        .wrap_err_with(|| format!("Unable to write metrics to {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .wrap_err_with(|| format!("Unable to move metrics into {}", path.display()))?;
+    Ok(())
+}
+
+/// Replaces the metrics for the `shopvac` job on the pushgateway.
+pub async fn push(url: &str, metrics: String) -> Result<()> {
+    let url = format!("{}/metrics/job/shopvac", url.trim_end_matches('/'));
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .put(&url)
+        .body(metrics)
+        .send()
+        .await?
+        .error_for_status()
+        .wrap_err_with(|| format!("Pushgateway at {} rejected metrics", url))?;
+    Ok(())
+}
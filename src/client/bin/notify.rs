@@ -0,0 +1,74 @@
+//! Post-run webhook notifications, the payload carries a Slack compatible
+//! `text` field alongside the full run summary.
+use std::time::Duration;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::report::RunSummary;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyOn {
+    /// Only when at least one deletion failed
+    Failure,
+    /// After every run
+    Always,
+    /// When deletions or failures exceed --notify-threshold
+    Threshold,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    text: String,
+    #[serde(flatten)]
+    summary: &'a RunSummary,
+}
+
+pub fn should_notify(on: NotifyOn, threshold: usize, summary: &RunSummary) -> bool {
+    match on {
+        NotifyOn::Failure => summary.failed > 0,
+        NotifyOn::Always => true,
+        NotifyOn::Threshold => summary.deleted > threshold || summary.failed > threshold,
+    }
+}
+
+/// Delivers the summary to the webhook. Errors are logged rather than
+/// returned, a flaky chat integration shouldn't fail the cleanup itself.
+pub async fn send(url: &str, summary: &RunSummary) {
+    let text = format!(
+        "{}shopvac {}matched {} pods across {} namespaces: {} deleted, {} failed, {} skipped",
+        if summary.dry_run { "[dry run] " } else { "" },
+        summary
+            .scanned
+            .map(|n| format!("scanned {} pods and ", n))
+            .unwrap_or_default(),
+        summary.matched,
+        summary.namespaces.len(),
+        summary.deleted,
+        summary.failed,
+        summary.skipped,
+    );
+
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Unable to build webhook client: {}", e);
+            return;
+        }
+    };
+
+    let res = client
+        .post(url)
+        .json(&Payload { text, summary })
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    match res {
+        Ok(_) => tracing::info!("Sent run notification to webhook"),
+        Err(e) => tracing::error!("Failed to deliver webhook notification: {}", e),
+    }
+}
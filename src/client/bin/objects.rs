@@ -0,0 +1,130 @@
+//! What `--resource pvc`, `--resource replicaset` and `--gvk` share: all scan
+//! whole objects up front and then delete them one by one.
+use std::fmt::Debug;
+
+use color_eyre::eyre::{bail, Result};
+use kube::Client;
+use serde::de::DeserializeOwned;
+use shopvac::Outcome;
+
+use crate::{
+    audit::{AuditLog, Audited},
+    confirm,
+    report::RunSummary,
+    stop_deleting, Args,
+};
+
+/// Refuses the flags that only make sense for pods.
+pub fn reject_pod_flags(args: &Args) -> Result<()> {
+    if !args.phase.is_empty()
+        || !args.reason.is_empty()
+        || args.image_pattern.is_some()
+        || args.exclude_pod_pattern.is_some()
+        || !args.owner_kind.is_empty()
+        || args.naked_only
+        || args.explain
+        || args.completed_for.is_some()
+        || args.orphaned
+        || args.skip_recently_active.is_some()
+        || args.exclude_running_longer_than.is_some()
+    {
+        bail!(
+            "--phase, --reason, --image-pattern, --exclude-pod-pattern, --owner-kind, \
+             --naked-only, --explain, --completed-for, --orphaned, --skip-recently-active \
+             and --exclude-running-longer-than only apply to --resource pod"
+        );
+    }
+
+    if args.diff_against.is_some() || args.state_file.is_some() {
+        bail!("--diff-against and --state-file only apply to --resource pod");
+    }
+    Ok(())
+}
+
+/// Deletes what a scan `found`, or records it as a dry run, `noun` names
+/// them in the logs, e.g. `claims`.
+#[allow(clippy::too_many_arguments)]
+pub async fn clean_found<K>(
+    args: &Args,
+    client: Client,
+    found: &[K],
+    dyntype: K::DynamicType,
+    noun: &str,
+    target: &str,
+    summary: &mut RunSummary,
+    audit: &mut Option<AuditLog>,
+) -> Result<()>
+where
+    K: Audited + Clone + DeserializeOwned + Debug,
+{
+    tracing::info!("Total of {} {} to delete found.", found.len(), noun);
+    if let Some(max) = args.max_pods {
+        if found.len() > max {
+            tracing::error!(
+                "Matched {} {}, more than --max-pods {}, refusing to continue.",
+                found.len(),
+                noun,
+                max
+            );
+            bail!(
+                "Matched {} count exceeds --max-pods, check the selectors",
+                noun
+            );
+        }
+    }
+
+    if args.all_namespaces && args.actually_delete && !args.yes && !found.is_empty() {
+        confirm(&format!(
+            "About to delete {} {} across all namespaces in {}.",
+            found.len(),
+            noun,
+            target
+        ))?;
+    }
+
+    if args.actually_delete || args.server_dry_run {
+        tracing::info!("Starting deletions...");
+
+        let report = shopvac::delete_objects(
+            client,
+            found,
+            dyntype,
+            args.delete_options(),
+            stop_deleting(args.watch),
+        )
+        .await;
+        for (obj, outcome) in &report.outcomes {
+            summary.record(*obj, outcome);
+            if let Some(audit) = audit.as_mut() {
+                audit.record(*obj, outcome)?;
+            }
+        }
+
+        tracing::info!(
+            "{} {} {}, {} failed ({} forbidden), {} not attempted.",
+            if args.server_dry_run {
+                "Server dry run would delete"
+            } else {
+                "Deleted"
+            },
+            summary.deleted,
+            noun,
+            summary.failed,
+            summary.forbidden,
+            summary.skipped
+        );
+    } else {
+        let outcome = Outcome::Skipped {
+            reason: "dry run".to_string(),
+        };
+        for obj in found {
+            summary.record(obj, &outcome);
+            if let Some(audit) = audit.as_mut() {
+                audit.record(obj, &outcome)?;
+            }
+        }
+        tracing::info!("Dry run initiated! Nothing was deleted.")
+    }
+
+    Ok(())
+}
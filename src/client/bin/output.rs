@@ -0,0 +1,155 @@
+//! Human readable rendering of the matched pods.
+use std::{collections::BTreeSet, fs, path::Path};
+
+use chrono::offset;
+use clap::ValueEnum;
+use color_eyre::eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use shopvac::{format_age, PodTarget};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// A log line per matched pod
+    Log,
+    /// An aligned table of matched pods printed to stdout
+    Table,
+    /// The matched pods as JSON on stdout, sorted so that runs can be
+    /// compared with --diff-against. Logs go to stderr
+    Json,
+}
+
+/// A matched pod as written by `--output json`, leaving out anything that
+/// changes between runs without the pod changing, such as its age.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct MatchedPod {
+    namespace: String,
+    name: String,
+    created: Option<String>,
+    phase: Option<String>,
+}
+
+impl MatchedPod {
+    fn of(target: &PodTarget) -> Self {
+        let p = &target.pod;
+        Self {
+            namespace: target.namespace(),
+            name: target.name(),
+            created: p
+                .metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|ct| ct.0.to_rfc3339()),
+            phase: p.status.as_ref().and_then(|s| s.phase.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MatchSet {
+    pods: Vec<MatchedPod>,
+}
+
+/// Prints the matched pods sorted by namespace and name, whatever order
+/// they're deleted in.
+pub fn print_json(targets: &[PodTarget]) -> Result<()> {
+    let mut pods: Vec<MatchedPod> = targets.iter().map(MatchedPod::of).collect();
+    pods.sort();
+    println!("{}", serde_json::to_string_pretty(&MatchSet { pods })?);
+    Ok(())
+}
+
+/// Compares the matched pods against an earlier `--output json` file and
+/// prints the pods that are newly matched and the ones no longer matched.
+///
+/// Goes to stderr with `--output json`, so the new file stays valid JSON.
+pub fn print_diff(previous: &Path, targets: &[PodTarget], to_stderr: bool) -> Result<()> {
+    let raw = fs::read_to_string(previous)
+        .wrap_err_with(|| format!("Unable to read {}", previous.display()))?;
+    let before: MatchSet = serde_json::from_str(&raw).wrap_err_with(|| {
+        format!(
+            "{} isn't the --output json of an earlier run",
+            previous.display()
+        )
+    })?;
+    let before: BTreeSet<(String, String)> = before
+        .pods
+        .into_iter()
+        .map(|p| (p.namespace, p.name))
+        .collect();
+    let now: BTreeSet<(String, String)> =
+        targets.iter().map(|t| (t.namespace(), t.name())).collect();
+
+    let print = |line: String| {
+        if to_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    };
+    let added: Vec<_> = now.difference(&before).collect();
+    let removed: Vec<_> = before.difference(&now).collect();
+    for (ns, name) in &added {
+        print(format!("+ {}:{}", ns, name));
+    }
+    for (ns, name) in &removed {
+        print(format!("- {}:{}", ns, name));
+    }
+    print(format!(
+        "\n{} newly matched, {} no longer matched, {} unchanged since {}",
+        added.len(),
+        removed.len(),
+        now.len() - added.len(),
+        previous.display()
+    ));
+    Ok(())
+}
+
+pub fn print_table(targets: &[PodTarget]) {
+    let now = offset::Utc::now();
+    let rows: Vec<[String; 4]> = targets
+        .iter()
+        .map(|t| {
+            let p = &t.pod;
+            [
+                t.namespace(),
+                t.name(),
+                p.metadata
+                    .creation_timestamp
+                    .as_ref()
+                    .map(|ct| format_age(now - ct.0))
+                    .unwrap_or_else(|| "<unknown>".to_string()),
+                p.status
+                    .as_ref()
+                    .and_then(|s| s.phase.clone())
+                    .unwrap_or_else(|| "<unknown>".to_string()),
+            ]
+        })
+        .collect();
+
+    let header = ["NAMESPACE", "NAME", "AGE", "PHASE"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let print_row = |cells: [&str; 4]| {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            cells[0],
+            cells[1],
+            cells[2],
+            cells[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+    };
+    print_row(header);
+    for row in &rows {
+        print_row([&row[0], &row[1], &row[2], &row[3]]);
+    }
+    println!("\nTotal: {} pods", rows.len());
+}
@@ -0,0 +1,162 @@
+//! Canned flag bundles for the workloads shopvac usually gets pointed at.
+use clap::{ArgMatches, ValueEnum, ValueSource};
+
+use shopvac::filter::PodPhase;
+
+use crate::Args;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum PresetName {
+    Tekton,
+    Airflow,
+    ArgoWorkflows,
+    Spark,
+    /// Print what each preset expands to and exit
+    List,
+}
+
+pub struct Preset {
+    pub name: PresetName,
+    pub description: &'static str,
+    pub label_selector: &'static str,
+    pub phases: &'static [PodPhase],
+    pub older_than_hours: u32,
+    pub protection_annotation: &'static str,
+}
+
+const FINISHED: &[PodPhase] = &[PodPhase::Succeeded, PodPhase::Failed];
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: PresetName::Tekton,
+        description: "Finished TaskRun pods",
+        label_selector: "tekton.dev/taskRun",
+        phases: FINISHED,
+        older_than_hours: 24,
+        protection_annotation: "shopvac.io/protect",
+    },
+    Preset {
+        name: PresetName::Airflow,
+        description: "Finished KubernetesExecutor worker pods",
+        label_selector: "kubernetes_executor=True",
+        phases: FINISHED,
+        older_than_hours: 24,
+        protection_annotation: "shopvac.io/protect",
+    },
+    Preset {
+        name: PresetName::ArgoWorkflows,
+        description: "Finished Argo Workflow step pods",
+        label_selector: "workflows.argoproj.io/workflow",
+        phases: FINISHED,
+        older_than_hours: 48,
+        protection_annotation: "shopvac.io/protect",
+    },
+    Preset {
+        name: PresetName::Spark,
+        description: "Finished Spark driver and executor pods",
+        label_selector: "spark-role",
+        phases: FINISHED,
+        older_than_hours: 72,
+        protection_annotation: "shopvac.io/protect",
+    },
+];
+
+impl PresetName {
+    pub fn preset(self) -> Option<&'static Preset> {
+        PRESETS.iter().find(|p| p.name == self)
+    }
+}
+
+impl Preset {
+    /// Fills in any argument that wasn't given on the command line or through
+    /// the environment, so explicit flags always win over the preset.
+    pub fn apply(&self, args: &mut Args, matches: &ArgMatches) {
+        let explicit = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable)
+            )
+        };
+
+        if !explicit("label-selector") {
+            args.label_selector = Some(self.label_selector.to_string());
+        }
+        if !explicit("phase") {
+            args.phase = self.phases.to_vec();
+        }
+        if !explicit("older-than") && !explicit("older-than-hours") {
+            args.older_than_hours = self.older_than_hours;
+        }
+        if !explicit("protection-annotation") {
+            args.protection_annotation = Some(self.protection_annotation.to_string());
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let phases: Vec<&str> = self.phases.iter().map(|p| p.as_str()).collect();
+        format!(
+            "{:<15} {}\n{:<15} --label-selector {} --phase {} --older-than-hours {} --protection-annotation {}",
+            self.name
+                .to_possible_value()
+                .map(|v| v.get_name())
+                .unwrap_or_default(),
+            self.description,
+            "",
+            self.label_selector,
+            phases.join(","),
+            self.older_than_hours,
+            self.protection_annotation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shopvac::filter::PodPhase;
+
+    use super::PresetName;
+    use crate::testing::parse;
+
+    #[test]
+    fn presets_expand_to_their_flags() {
+        let expected = [
+            (PresetName::Tekton, "tekton.dev/taskRun", 24),
+            (PresetName::Airflow, "kubernetes_executor=True", 24),
+            (
+                PresetName::ArgoWorkflows,
+                "workflows.argoproj.io/workflow",
+                48,
+            ),
+            (PresetName::Spark, "spark-role", 72),
+        ];
+        for (name, label_selector, hours) in expected {
+            let (mut args, matches) = parse(&[]);
+            name.preset().unwrap().apply(&mut args, &matches);
+
+            assert_eq!(args.label_selector.as_deref(), Some(label_selector));
+            assert_eq!(args.phase, vec![PodPhase::Succeeded, PodPhase::Failed]);
+            assert_eq!(args.older_than_hours(), hours, "{:?}", name);
+            assert_eq!(
+                args.protection_annotation.as_deref(),
+                Some("shopvac.io/protect")
+            );
+        }
+        assert!(PresetName::List.preset().is_none());
+    }
+
+    #[test]
+    fn explicit_flags_survive_the_preset() {
+        let (mut args, matches) = parse(&["--older-than", "10", "-l", "app=etl"]);
+        let tekton = PresetName::Tekton.preset().unwrap();
+        tekton.apply(&mut args, &matches);
+
+        assert_eq!(args.older_than_hours(), 240);
+        assert_eq!(args.label_selector.as_deref(), Some("app=etl"));
+        // the rest still comes from the preset
+        assert_eq!(args.phase, vec![PodPhase::Succeeded, PodPhase::Failed]);
+
+        let (mut args, matches) = parse(&["--older-than-hours", "6"]);
+        tekton.apply(&mut args, &matches);
+        assert_eq!(args.older_than_hours(), 6);
+    }
+}
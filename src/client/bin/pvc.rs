@@ -0,0 +1,50 @@
+//! `--resource pvc`, cleaning up claims that outlived their pods.
+use chrono::offset;
+use color_eyre::eyre::Result;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Pod};
+use kube::{api::Api, Client, ResourceExt};
+use shopvac::ScanOptions;
+
+use crate::{
+    audit::AuditLog,
+    objects::{clean_found, reject_pod_flags},
+    report::RunSummary,
+    Args,
+};
+
+pub async fn clean(
+    args: &Args,
+    client: Client,
+    scan: &ScanOptions,
+    target: &str,
+    summary: &mut RunSummary,
+    audit: &mut Option<AuditLog>,
+) -> Result<()> {
+    reject_pod_flags(args)?;
+
+    let (pvcs, pods): (Api<PersistentVolumeClaim>, Api<Pod>) = match &args.namespace {
+        Some(ns) => (
+            Api::namespaced(client.clone(), ns),
+            Api::namespaced(client.clone(), ns),
+        ),
+        None => (Api::all(client.clone()), Api::all(client.clone())),
+    };
+
+    let claims = shopvac::scan_pvcs(&pvcs, &pods, scan).await?;
+    let now = offset::Utc::now();
+    for c in &claims {
+        let age = c
+            .metadata
+            .creation_timestamp
+            .as_ref()
+            .map_or(0, |ct| (now - ct.0).num_hours());
+        tracing::info!(
+            "Found orphaned claim! {}:{}, duration: {:?} hours old",
+            c.namespace().unwrap_or_default(),
+            c.name(),
+            age
+        );
+    }
+
+    clean_found(args, client, &claims, (), "claims", target, summary, audit).await
+}
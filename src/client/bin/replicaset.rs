@@ -0,0 +1,60 @@
+//! `--resource replicaset`, cleaning up ReplicaSets scaled to zero.
+use chrono::offset;
+use color_eyre::eyre::Result;
+use k8s_openapi::api::{apps::v1::ReplicaSet, core::v1::Pod};
+use kube::{api::Api, Client, ResourceExt};
+use shopvac::ScanOptions;
+
+use crate::{
+    audit::AuditLog,
+    objects::{clean_found, reject_pod_flags},
+    report::RunSummary,
+    Args,
+};
+
+pub async fn clean(
+    args: &Args,
+    client: Client,
+    scan: &ScanOptions,
+    target: &str,
+    summary: &mut RunSummary,
+    audit: &mut Option<AuditLog>,
+) -> Result<()> {
+    reject_pod_flags(args)?;
+
+    let (replicasets, pods): (Api<ReplicaSet>, Api<Pod>) = match &args.namespace {
+        Some(ns) => (
+            Api::namespaced(client.clone(), ns),
+            Api::namespaced(client.clone(), ns),
+        ),
+        None => (Api::all(client.clone()), Api::all(client.clone())),
+    };
+
+    let found = shopvac::scan_replicasets(&replicasets, &pods, scan).await?;
+    let now = offset::Utc::now();
+    for rs in &found {
+        let age = rs
+            .metadata
+            .creation_timestamp
+            .as_ref()
+            .map_or(0, |ct| (now - ct.0).num_hours());
+        tracing::info!(
+            "Found empty ReplicaSet! {}:{}, duration: {:?} hours old",
+            rs.namespace().unwrap_or_default(),
+            rs.name(),
+            age
+        );
+    }
+
+    clean_found(
+        args,
+        client,
+        &found,
+        (),
+        "ReplicaSets",
+        target,
+        summary,
+        audit,
+    )
+    .await
+}
@@ -0,0 +1,179 @@
+//! End of run summary, and persisting it somewhere that outlives the pod.
+use std::collections::BTreeMap;
+
+use chrono::offset;
+use color_eyre::eyre::{Result, WrapErr};
+use k8s_openapi::{api::core::v1::ConfigMap, apimachinery::pkg::apis::meta::v1::OwnerReference};
+use kube::{
+    api::{Api, ObjectMeta, Patch, PatchParams},
+    ResourceExt,
+};
+use serde::Serialize;
+use shopvac::Outcome;
+
+use crate::audit::Filters;
+
+#[derive(Debug, Default, Serialize)]
+pub struct NamespaceCounts {
+    pub matched: usize,
+    pub deleted: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    pub namespace: String,
+    pub pod: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub timestamp: String,
+    pub dry_run: bool,
+    pub parameters: Filters,
+    /// Pods looked at, matching or not. Unknown for streamed runs
+    pub scanned: Option<usize>,
+    pub matched: usize,
+    pub deleted: usize,
+    /// Deletes accepted while the object was still shutting down, a subset
+    /// of `deleted`
+    pub terminating: usize,
+    /// Objects something else deleted first, neither deleted nor failed
+    pub already_gone: usize,
+    pub failed: usize,
+    /// Failures the apiserver refused with a 403, a subset of `failed`
+    pub forbidden: usize,
+    pub skipped: usize,
+    /// A shutdown stopped the run early. Streamed runs never see the pods
+    /// they didn't get to, so these aren't counted as skipped
+    pub interrupted: bool,
+    pub namespaces: BTreeMap<String, NamespaceCounts>,
+    pub failures: Vec<Failure>,
+}
+
+impl RunSummary {
+    pub fn new(parameters: Filters, dry_run: bool) -> Self {
+        Self {
+            timestamp: offset::Utc::now().to_rfc3339(),
+            dry_run,
+            parameters,
+            scanned: None,
+            matched: 0,
+            deleted: 0,
+            terminating: 0,
+            already_gone: 0,
+            failed: 0,
+            forbidden: 0,
+            skipped: 0,
+            interrupted: false,
+            namespaces: BTreeMap::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Tallies the final outcome for a matched object, every matched object
+    /// is expected to be recorded exactly once.
+    pub fn record(&mut self, obj: &impl ResourceExt, outcome: &Outcome) {
+        let namespace = obj.namespace().unwrap_or_default();
+        let counts = self.namespaces.entry(namespace.clone()).or_default();
+
+        self.matched += 1;
+        counts.matched += 1;
+        match outcome {
+            Outcome::Deleted => {
+                self.deleted += 1;
+                counts.deleted += 1;
+            }
+            Outcome::Terminating => {
+                self.deleted += 1;
+                self.terminating += 1;
+                counts.deleted += 1;
+            }
+            Outcome::AlreadyGone => self.already_gone += 1,
+            Outcome::Failed { error, code } => {
+                self.failed += 1;
+                if *code == Some(403) {
+                    self.forbidden += 1;
+                }
+                counts.failed += 1;
+                self.failures.push(Failure {
+                    namespace,
+                    pod: obj.name(),
+                    error: error.clone(),
+                });
+            }
+            Outcome::Skipped { .. } => {
+                self.skipped += 1;
+                counts.skipped += 1;
+            }
+        }
+    }
+
+    /// Logs a per-namespace breakdown, worst offenders first.
+    pub fn log_namespace_breakdown(&self) {
+        let mut rows: Vec<(&String, &NamespaceCounts)> = self.namespaces.iter().collect();
+        rows.sort_by(|(a_ns, a), (b_ns, b)| {
+            (b.deleted, b.matched)
+                .cmp(&(a.deleted, a.matched))
+                .then_with(|| a_ns.cmp(b_ns))
+        });
+
+        let width = rows
+            .iter()
+            .map(|(ns, _)| ns.len())
+            .max()
+            .unwrap_or(0)
+            .max("NAMESPACE".len());
+        tracing::info!(
+            "{:<width$}  {:>7}  {:>7}  {:>6}",
+            "NAMESPACE",
+            "MATCHED",
+            "DELETED",
+            "FAILED",
+            width = width
+        );
+        for (ns, counts) in rows {
+            tracing::info!(
+                "{:<width$}  {:>7}  {:>7}  {:>6}",
+                ns,
+                counts.matched,
+                counts.deleted,
+                counts.failed,
+                width = width
+            );
+        }
+    }
+}
+
+/// Writes the summary as `summary.json` into the named ConfigMap, replacing
+/// whatever report a previous run left behind.
+pub async fn write_configmap(
+    api: &Api<ConfigMap>,
+    name: &str,
+    owner: Option<OwnerReference>,
+    summary: &RunSummary,
+) -> Result<()> {
+    let cm = ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            owner_references: owner.map(|o| vec![o]),
+            ..ObjectMeta::default()
+        },
+        data: Some(BTreeMap::from([(
+            "summary.json".to_string(),
+            serde_json::to_string_pretty(summary)?,
+        )])),
+        ..Default::default()
+    };
+
+    api.patch(
+        name,
+        &PatchParams::apply("shopvac").force(),
+        &Patch::Apply(&cm),
+    )
+    .await
+    .wrap_err_with(|| format!("Unable to write report ConfigMap {}", name))?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! `--state-file`, remembering when the last run started so the next one
+//! only looks at pods that could have become old enough since.
+use std::{fs, io::ErrorKind, path::Path};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+
+/// When the last recorded run started, `None` before the first one.
+pub fn read(path: &Path) -> Result<Option<DateTime<Utc>>> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).wrap_err_with(|| format!("Unable to read state file {}", path.display()))
+        }
+    };
+    let last_run = DateTime::parse_from_rfc3339(raw.trim())
+        .wrap_err_with(|| format!("Invalid state file {}", path.display()))?;
+    Ok(Some(last_run.with_timezone(&Utc)))
+}
+
+/// Records the start of a run, replacing the file in one step so a crash
+/// never leaves half a timestamp behind.
+pub fn write(path: &Path, started: DateTime<Utc>) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, format!("{}\n", started.to_rfc3339()))
+        .and_then(|()| fs::rename(&tmp, path))
+        .wrap_err_with(|| format!("Unable to write state file {}", path.display()))
+}
@@ -0,0 +1,53 @@
+//! Fixtures shared by the unit tests.
+use std::{
+    env,
+    sync::{Mutex, MutexGuard},
+};
+
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+
+use crate::{Args, Cli};
+
+/// Held while flags are parsed, the environment is shared by every test.
+static ENV: Mutex<()> = Mutex::new(());
+
+/// The `SHOPVAC_*` variables set for a parse, removed again on drop.
+struct Env<'a> {
+    vars: &'a [(&'a str, &'a str)],
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl<'a> Env<'a> {
+    fn set(vars: &'a [(&'a str, &'a str)]) -> Self {
+        // a failed assertion elsewhere poisons the lock, not the environment
+        let lock = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, value) in vars {
+            env::set_var(key, value);
+        }
+        Self { vars, _lock: lock }
+    }
+}
+
+impl Drop for Env<'_> {
+    fn drop(&mut self) {
+        for (key, _) in self.vars {
+            env::remove_var(key);
+        }
+    }
+}
+
+/// Parses `argv`, flags only without a subcommand, with `vars` set in the
+/// environment.
+pub fn parse_with_env(vars: &[(&str, &str)], argv: &[&str]) -> (Args, ArgMatches) {
+    let _env = Env::set(vars);
+    let matches = Cli::command()
+        .try_get_matches_from(std::iter::once("shopvac").chain(argv.iter().copied()))
+        .unwrap();
+    let cli = Cli::from_arg_matches(&matches).unwrap();
+    (cli.args, matches)
+}
+
+/// Parses `argv` with no `SHOPVAC_*` variables set.
+pub fn parse(argv: &[&str]) -> (Args, ArgMatches) {
+    parse_with_env(&[], argv)
+}
@@ -0,0 +1,148 @@
+//! `--watch`, running cleanups on an interval from a single long lived
+//! process instead of a CronJob.
+use std::{
+    convert::TryFrom,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre::{eyre, Report, Result};
+use futures::{future, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{Api, ListParams},
+    runtime::{
+        reflector::{self, store::Writer, Store},
+        watcher,
+    },
+};
+use serde::Deserialize;
+
+/// A duration such as `90s`, `10m`, `1h` or `2d`, for the time between
+/// cleanup cycles and --completed-for.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Interval(pub Duration);
+
+impl FromStr for Interval {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let number: u64 = number
+            .parse()
+            .map_err(|_| eyre!("invalid interval {:?}, expected e.g. 10m", s))?;
+        let unit_seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            _ => {
+                return Err(eyre!(
+                    "invalid interval {:?}, expected a s, m, h or d suffix",
+                    s
+                ))
+            }
+        };
+        let seconds = number
+            .checked_mul(unit_seconds)
+            .ok_or_else(|| eyre!("invalid interval {:?}, too long", s))?;
+        if seconds == 0 {
+            return Err(eyre!("the interval must be longer than zero"));
+        }
+        Ok(Self(Duration::from_secs(seconds)))
+    }
+}
+
+impl TryFrom<String> for Interval {
+    type Error = Report;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Server side timeout of a watch, in seconds: short of `request_timeout`
+/// and below the 295s the apiserver accepts.
+fn watch_timeout(request_timeout: Duration) -> u32 {
+    request_timeout.as_secs().saturating_sub(5).clamp(1, 290) as u32
+}
+
+/// Upper bound on the delay before re-establishing the pod watch after errors.
+const WATCH_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Where a cycle gets its pods from.
+pub enum PodSource {
+    /// A fresh list call every time
+    List(Api<Pod>),
+    /// The reflector store kept up to date in between --watch cycles
+    Cache(Store<Pod>),
+}
+
+/// Starts a reflector over the pods matching `lp`, so cycles after the first
+/// can read from memory instead of listing every pod again.
+///
+/// Watches are closed by the apiserver before `request_timeout` would cut
+/// off a quiet one. After errors the watch waits before listing again,
+/// doubling up to `WATCH_BACKOFF_MAX` while they keep coming.
+pub fn cache(api: Api<Pod>, lp: ListParams, request_timeout: Duration) -> Store<Pod> {
+    let lp = lp.timeout(watch_timeout(request_timeout));
+    let writer = Writer::default();
+    let store = writer.as_reader();
+    let failures = Arc::new(AtomicU32::new(0));
+    tokio::spawn(
+        reflector::reflector(writer, watcher(api, lp))
+            .then(move |event| {
+                let failures = failures.clone();
+                async move {
+                    match event {
+                        Ok(_) => failures.store(0, Ordering::Relaxed),
+                        Err(e) => {
+                            let failed = failures.fetch_add(1, Ordering::Relaxed) + 1;
+                            let delay =
+                                WATCH_BACKOFF_MAX.min(Duration::from_secs(1 << failed.min(6)));
+                            tracing::warn!(
+                                "Pod watch failed ({} consecutive), re-establishing it in {:?}: {}",
+                                failed,
+                                delay,
+                                e
+                            );
+                            tokio::time::sleep(delay).await;
+                        }
+                    }
+                }
+            })
+            .for_each(|()| future::ready(())),
+    );
+    store
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Interval;
+
+    #[test]
+    fn intervals_parse_by_unit() {
+        for (s, secs) in [("90s", 90), ("10m", 600), ("1h", 3600), ("2d", 172_800)] {
+            assert_eq!(s.parse::<Interval>().unwrap().0, Duration::from_secs(secs));
+        }
+        for invalid in ["0m", "10", "10w", "m", ""] {
+            assert!(invalid.parse::<Interval>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn overflowing_intervals_are_rejected() {
+        let err = "999999999999999d".parse::<Interval>().unwrap_err();
+        assert!(err.to_string().contains("invalid interval"), "{}", err);
+        assert!(format!("{}h", u64::MAX).parse::<Interval>().is_err());
+    }
+}
@@ -1,36 +1,64 @@
 #![forbid(unsafe_code)]
 
-use anyhow::{bail, Result};
-use clap::Parser;
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand};
+use croner::Cron;
 use futures::prelude::*;
 use k8s_openapi::api::{
-    batch::v1::{CronJob, CronJobSpec},
-    core::v1::ServiceAccount,
-    rbac::v1::RoleBinding,
+    batch::v1::{CronJob, CronJobSpec, Job},
+    core::v1::{
+        Affinity, PodSecurityContext, ResourceRequirements, SecurityContext, ServiceAccount,
+        Toleration,
+    },
+    rbac::v1::{Role, RoleBinding},
 };
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::apimachinery::pkg::version::Info;
 // use kube::{api::ListParams, runtime::watcher::Event, ResourceExt};
 use kube::{
-    api::{Api, ListParams, ObjectMeta, Patch, PatchParams, Resource},
+    api::{Api, DeleteParams, ListParams, ObjectMeta, Patch, PatchParams, Resource, ResourceExt},
     runtime::controller::Action,
-    runtime::controller::{Context, Controller},
-    Client, CustomResource,
+    runtime::controller::{self, applier, trigger_owners, trigger_self, Context},
+    runtime::events::{Event, EventType, Recorder, Reporter},
+    runtime::finalizer::{self, finalizer},
+    runtime::reflector::{reflector, store::Writer, ObjectRef},
+    runtime::utils::{try_flatten_applied, try_flatten_touched},
+    runtime::wait::{await_condition, conditions},
+    runtime::watcher,
+    Client, CustomResource, CustomResourceExt,
 };
+use rand::Rng;
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::json;
-use std::{io::BufRead, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+    io::BufRead,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
 use thiserror::Error;
-use tokio::time;
+use tokio::{signal, time};
+
+mod metrics;
+
+use metrics::Metrics;
 
 #[derive(Parser)]
 #[clap(version)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// The tracing filter used for logs
     #[clap(long, env = "SHOPVAC_LOG", default_value = "debug,kube=info")]
     log_level: kubert::LogFilter,
 
     /// The logging format
-    #[clap(long, default_value = "plain")]
+    #[clap(long, default_value = "plain", env = "SHOPVAC_LOG_FORMAT")]
     log_format: kubert::LogFormat,
 
     #[clap(flatten)]
@@ -39,41 +67,603 @@ struct Args {
     #[clap(flatten)]
     admin: kubert::AdminArgs,
 
-    /// Exit after the first update is received
-    #[clap(long)]
+    /// Exit once every PodCleaner that existed at startup has been
+    /// reconciled, e.g. for CI smoke tests or migration jobs. Exits non-zero
+    /// when any reconcile failed or --timeout passed first
+    #[clap(long, env = "SHOPVAC_EXIT")]
     exit: bool,
 
-    /// The amount of time to wait for the first update
-    #[clap(long, default_value = "10s")]
+    /// The amount of time to wait for the Kubernetes client to initialize and
+    /// any CRD to be established, and with --exit for the whole run
+    #[clap(long, default_value = "10s", env = "SHOPVAC_TIMEOUT")]
     timeout: Timeout,
 
-    /// An optional pod selector
-    #[clap(long, short = 'l')]
+    /// Only manage PodCleaners matching this label selector, e.g.
+    /// `team=data-eng`, to shard them across controller instances. Their
+    /// CronJobs carry the PodCleaner's labels and are watched with the same
+    /// selector. Shards must not overlap: a PodCleaner two instances select
+    /// is reconciled by both, one no instance selects by none
+    #[clap(long, short = 'l', env = "SHOPVAC_SELECTOR")]
     selector: Option<String>,
+
+    /// Only watch PodCleaners (and their CronJobs) in this namespace, so the
+    /// controller can run with namespace scoped RBAC
+    #[clap(long, short = 'n', env = "SHOPVAC_NAMESPACE")]
+    namespace: Option<String>,
+
+    /// Never create ServiceAccounts, Roles or RoleBindings, for clusters where
+    /// they are provisioned ahead of time
+    #[clap(long, env = "SHOPVAC_SKIP_RBAC")]
+    skip_rbac: bool,
+
+    /// Cleaner image for PodCleaners that don't set `spec.image`
+    #[clap(long, default_value = DEFAULT_IMAGE, env = "SHOPVAC_IMAGE")]
+    default_image: String,
+
+    /// Where to serve Prometheus metrics on `/metrics`, next to the admin
+    /// server's probes
+    #[clap(long, default_value = "0.0.0.0:9090", env = "SHOPVAC_METRICS_ADDR")]
+    metrics_addr: std::net::SocketAddr,
+
+    /// How long the watch may fail without a break before `/healthz` on the
+    /// metrics address reports the controller unhealthy
+    #[clap(long, default_value = "5m", env = "SHOPVAC_UNHEALTHY_AFTER")]
+    unhealthy_after: Timeout,
+
+    /// How often a healthy PodCleaner is reconciled again to repair drift of
+    /// its children, at least 10s. Changes to the PodCleaner or its CronJobs
+    /// are reconciled right away regardless, and PodCleaners whose last run
+    /// failed are checked every minute at most
+    #[clap(long, default_value = "5m", env = "SHOPVAC_REQUEUE_INTERVAL")]
+    requeue_interval: RequeueInterval,
+
+    /// Delay before retrying a failed reconcile, doubling with every
+    /// consecutive failure up to 5m
+    #[clap(long, default_value = "1s", env = "SHOPVAC_ERROR_BACKOFF")]
+    error_backoff: Timeout,
+
+    /// Install or update the PodCleaner CRD at startup and wait until it is
+    /// established. Needs RBAC to patch CustomResourceDefinitions, the
+    /// controller exits at once when it is missing
+    #[clap(long, env = "SHOPVAC_ENSURE_CRDS")]
+    ensure_crds: bool,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Print the PodCleaner CRD as YAML, e.g. for `kubectl apply -f -`,
+    /// instead of running the controller
+    Crd {
+        /// Server-side apply the CRD to the cluster and wait until it is
+        /// established, rather than printing it
+        #[clap(long)]
+        install: bool,
+    },
+}
+
+/// The cleaner released alongside this controller, so upgrading the
+/// controller is what changes the cleanup behaviour rather than a moving tag.
+const DEFAULT_IMAGE: &str = concat!("quay.io/wseaton/shopvac:v", env!("CARGO_PKG_VERSION"));
+
 #[derive(Debug, Error)]
 enum Error {
     #[error("Failed to create CronJob: {0}")]
     CronJobCreationFailed(#[source] kube::Error),
+    #[error("Failed to create ServiceAccount: {0}")]
+    ServiceAccountCreationFailed(#[source] kube::Error),
+    #[error("Failed to create Role: {0}")]
+    RoleCreationFailed(#[source] kube::Error),
+    #[error("Failed to create RoleBinding: {0}")]
+    RoleBindingCreationFailed(#[source] kube::Error),
+    #[error("Failed to delete CronJob: {0}")]
+    CronJobDeletionFailed(#[source] kube::Error),
+    #[error("Failed to update PodCleaner status: {0}")]
+    StatusPatchFailed(#[source] kube::Error),
+    #[error("Failed to record the cleanup deadline: {0}")]
+    CleanupDeadlinePatchFailed(#[source] kube::Error),
     #[error("MissingObjectKey: {0}")]
     MissingObjectKey(&'static str),
     #[error("Failed to create CronJobSpec")]
     CronJobSpecError,
+    #[error("{0}")]
+    FinalizerFailed(#[source] Box<finalizer::Error<Error>>),
+    #[error("{source} (attempt {attempts})")]
+    Retrying {
+        attempts: u32,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// The kind of child object that couldn't be applied, if that's what
+    /// went wrong.
+    fn child_kind(&self) -> Option<&'static str> {
+        match self {
+            Error::CronJobCreationFailed(_) => Some("CronJob"),
+            Error::ServiceAccountCreationFailed(_) => Some("ServiceAccount"),
+            Error::RoleCreationFailed(_) => Some("Role"),
+            Error::RoleBindingCreationFailed(_) => Some("RoleBinding"),
+            _ => None,
+        }
+    }
+
+    /// Whether retrying can't help until the PodCleaner is edited, e.g. the
+    /// apiserver or an admission webhook rejected a child as invalid.
+    fn is_permanent(&self) -> bool {
+        match self {
+            Error::Retrying { source, .. } => source.is_permanent(),
+            Error::FinalizerFailed(e) => match e.as_ref() {
+                finalizer::Error::ApplyFailed(e) | finalizer::Error::CleanupFailed(e) => {
+                    e.is_permanent()
+                }
+                _ => false,
+            },
+            Error::CronJobSpecError => true,
+            Error::CronJobCreationFailed(e)
+            | Error::ServiceAccountCreationFailed(e)
+            | Error::RoleCreationFailed(e)
+            | Error::RoleBindingCreationFailed(e) => {
+                matches!(e, kube::Error::Api(e) if e.code == 400 || e.code == 422)
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(CustomResource, Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[kube(group = "shopvac.io", version = "v1", kind = "PodCleaner")]
-#[kube(shortname = "pc", namespaced)]
+#[kube(
+    shortname = "pc",
+    namespaced,
+    status = "PodCleanerStatus",
+    category = "all"
+)]
+#[kube(printcolumn = r#"{"name":"Schedule","type":"string","jsonPath":".spec.schedule"}"#)]
+#[kube(
+    printcolumn = r#"{"name":"Older-Than","type":"integer","description":"Days","jsonPath":".spec.delete_older_than"}"#
+)]
+#[kube(printcolumn = r#"{"name":"Suspended","type":"boolean","jsonPath":".spec.suspend"}"#)]
+#[kube(printcolumn = r#"{"name":"Last-Run","type":"date","jsonPath":".status.lastSuccessfulRun"}"#)]
+#[kube(
+    printcolumn = r#"{"name":"Ready","type":"string","jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"Dry-Run","type":"string","priority":1,"jsonPath":".status.conditions[?(@.type==\"DryRun\")].status"}"#
+)]
 struct PodCleanerSpec {
-    /// Schedule in cron-style syntax
+    /// Schedule in cron-style syntax, five fields or a macro such as
+    /// `@hourly`
+    #[schemars(regex(
+        pattern = r"^(@(yearly|annually|monthly|weekly|daily|midnight|hourly)|\S+(\s+\S+){4})$"
+    ))]
     schedule: String,
-    delete_older_than: i8,
+    /// Age in days past which pods are deleted
+    #[schemars(range(min = 1))]
+    delete_older_than: u32,
+    /// More runs of the same cleanup on schedules of their own, e.g. an
+    /// aggressive one overnight next to a gentle one during the day. Each
+    /// gets its own CronJob next to the one for `schedule`
+    schedules: Option<Vec<ExtraSchedule>>,
     label_selector: Option<String>,
     field_selector: Option<String>,
+    /// Only delete pods in one of these phases
+    pod_phases: Option<Vec<PodPhase>>,
+    /// Never delete pods whose name matches this regex
+    exclude_pod_pattern: Option<String>,
+    /// Never delete pods carrying this annotation, unless its value is
+    /// "false"
+    protection_annotation: Option<String>,
+    /// Only delete pods owned by one of these kinds, e.g. `Job`
+    owner_kinds: Option<Vec<String>>,
+    /// Only delete pods without any owner, leaving everything a ReplicaSet,
+    /// Job or other controller manages alone. Defaults to false, which
+    /// considers owned and unowned pods alike. Can't be combined with
+    /// `owner_kinds`
+    naked_only: Option<bool>,
+    /// Skip a run entirely when more than this many pods match, which
+    /// usually means the selectors are wrong
+    #[schemars(range(min = 1))]
+    max_delete: Option<u32>,
+    /// Extra labels for the cleanup job pods, e.g. for cost allocation or
+    /// network policies
+    pod_labels: Option<BTreeMap<String, String>>,
+    /// Extra annotations for the cleanup job pods, e.g. to opt out of sidecar
+    /// injection
+    pod_annotations: Option<BTreeMap<String, String>>,
+    /// Keys of the PodCleaner's own labels to copy onto the cleanup jobs and
+    /// their pods. Its other children carry all of its labels already
+    propagate_labels: Option<Vec<String>>,
+    /// Keys of the PodCleaner's own annotations to copy onto the CronJob, the
+    /// cleanup jobs and their pods, and the RBAC objects
+    propagate_annotations: Option<Vec<String>>,
+    /// Labels for the cleanup jobs and their pods, taking precedence over
+    /// `propagate_labels`
+    job_labels: Option<BTreeMap<String, String>>,
+    /// Annotations for the cleanup jobs and their pods, taking precedence over
+    /// `propagate_annotations`
+    job_annotations: Option<BTreeMap<String, String>>,
+    /// Cleaner image, defaults to the one matching the controller version
+    image: Option<String>,
+    /// Pull policy for the cleaner image, the Kubernetes default applies
+    /// when unset
+    image_pull_policy: Option<ImagePullPolicy>,
+    /// Only record what each run would delete in its report ConfigMap, e.g.
+    /// to observe a new cleaner for a while before letting it loose
+    #[serde(default)]
+    dry_run: bool,
+    /// Pause the cleanups without deleting the PodCleaner, e.g. during an
+    /// incident. The CronJob and its job history are kept
+    #[serde(default)]
+    suspend: bool,
+    /// Webhook the cleanup job POSTs a run summary to, e.g. a Slack incoming
+    /// webhook
+    notify_url: Option<String>,
+    /// Create the ServiceAccount, Role and RoleBinding the cleanup job runs
+    /// with (the default). Turn off when RBAC is managed elsewhere
+    manage_rbac: Option<bool>,
+    /// Bring your own ServiceAccount for the cleanup job, no RBAC is
+    /// provisioned then. Defaults to `<name>-shopvac-sa`
+    service_account_name: Option<String>,
+    /// Requests and limits of the cleanup container, big namespaces may need
+    /// more memory than a LimitRange default
+    resources: Option<ResourceRequirements>,
+    /// Security context of the cleanup container, defaults to one that passes
+    /// the restricted PodSecurity profile
+    security_context: Option<SecurityContext>,
+    /// Security context of the cleanup pod, defaults to one that passes the
+    /// restricted PodSecurity profile
+    pod_security_context: Option<PodSecurityContext>,
+    /// Finished cleanup jobs kept around, defaults to 1
+    successful_jobs_history_limit: Option<i32>,
+    /// Failed cleanup jobs kept around, defaults to 1
+    failed_jobs_history_limit: Option<i32>,
+    /// Retries of a failed cleanup, defaults to 2
+    backoff_limit: Option<i32>,
+    /// Seconds a cleanup may run before it is stopped, defaults to 900
+    active_deadline_seconds: Option<i64>,
+    /// Seconds after which a finished cleanup job and its pod are removed,
+    /// defaults to a day
+    ttl_seconds_after_finished: Option<i32>,
+    /// What to do when a cleanup overruns into the next scheduled time,
+    /// defaults to `Forbid` so runs never fight over the same pods
+    #[serde(default)]
+    concurrency_policy: ConcurrencyPolicy,
+    /// Seconds after a missed scheduled time a cleanup may still start
+    starting_deadline_seconds: Option<i64>,
+    /// Node labels the cleanup pod must be scheduled on
+    node_selector: Option<BTreeMap<String, String>>,
+    /// Taints the cleanup pod tolerates, e.g. of a dedicated batch node pool
+    tolerations: Option<Vec<Toleration>>,
+    /// Scheduling constraints of the cleanup pod
+    affinity: Option<Affinity>,
+    /// PriorityClass of the cleanup pod
+    priority_class_name: Option<String>,
+    /// IANA time zone the schedule is in, e.g. `Europe/Berlin`, instead of
+    /// the kube-controller-manager's. Needs Kubernetes 1.25 or later
+    time_zone: Option<String>,
+}
+
+/// What the controller last did for a PodCleaner.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct PodCleanerStatus {
+    /// The `.metadata.generation` this status was written for
+    observed_generation: Option<i64>,
+    cron_job_name: Option<String>,
+    /// When the CronJob last started a cleanup
+    last_schedule_time: Option<String>,
+    /// When a cleanup last completed successfully
+    last_successful_run: Option<String>,
+    /// When a cleanup last failed
+    last_failed_run: Option<String>,
+    /// The job of the last failed cleanup, so each failure is counted once
+    last_failed_job: Option<String>,
+    /// Failed cleanups seen since the PodCleaner was created
+    failed_runs: Option<u32>,
+    /// ScheduleInvalid, FiltersInvalid, TimeZoneUnsupported, DryRun,
+    /// Suspended, RBACProvisioned, CronJobCreated, LastRunSucceeded (once a
+    /// cleanup has finished) and Ready
+    conditions: Vec<PodCleanerCondition>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct PodCleanerCondition {
+    #[serde(rename = "type")]
+    type_: String,
+    /// "True" or "False"
+    status: String,
+    reason: String,
+    message: Option<String>,
+    last_transition_time: String,
+}
+
+/// A further run of a PodCleaner's cleanup.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+struct ExtraSchedule {
+    /// Names the CronJob, `<name>-clean-job-<this>`
+    #[schemars(regex(pattern = r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?$"))]
+    name: String,
+    /// Schedule in cron-style syntax, five fields or a macro such as
+    /// `@hourly`
+    #[schemars(regex(
+        pattern = r"^(@(yearly|annually|monthly|weekly|daily|midnight|hourly)|\S+(\s+\S+){4})$"
+    ))]
+    schedule: String,
+    /// Age in days past which pods are deleted by this run
+    #[schemars(range(min = 1))]
+    delete_older_than: u32,
+}
+
+/// A pod `status.phase`, as the cleaner's `--phase` takes it.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+enum PodPhase {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+enum ImagePullPolicy {
+    Always,
+    IfNotPresent,
+    Never,
+}
+
+/// What the CronJob does when a cleanup is still running at the next
+/// scheduled time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+enum ConcurrencyPolicy {
+    /// Start another cleanup alongside it
+    Allow,
+    /// Skip the new run
+    #[default]
+    Forbid,
+    /// Stop the running cleanup and start the new one
+    Replace,
 }
 
+/// Reconciles a PodCleaner, counting consecutive failures so `error_policy`
+/// can back off.
 async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Action, Error> {
+    let key = ObjectRef::from_obj(generator.as_ref());
+    let started = time::Instant::now();
+    let result = match generator.namespace() {
+        Some(ns) => {
+            // the finalizer is released as soon as the cleanup returns, so
+            // waiting for running cleanups has to happen before it gets there
+            let drained = if generator.meta().deletion_timestamp.is_some()
+                && generator.finalizers().iter().any(|f| f == FINALIZER)
+            {
+                drain_cleaner(&generator, ctx.get_ref().client.clone()).await
+            } else {
+                Ok(None)
+            };
+            match drained {
+                Ok(Some(action)) => Ok(action),
+                Ok(None) => {
+                    let pcs = Api::<PodCleaner>::namespaced(ctx.get_ref().client.clone(), &ns);
+                    finalizer(&pcs, FINALIZER, generator.clone(), |event| async {
+                        match event {
+                            finalizer::Event::Apply(generator) => {
+                                reconcile_cleaner(generator, ctx.clone()).await
+                            }
+                            finalizer::Event::Cleanup(generator) => {
+                                cleanup_cleaner(generator, ctx.clone()).await
+                            }
+                        }
+                    })
+                    .await
+                    .map_err(|e| Error::FinalizerFailed(Box::new(e)))
+                }
+                Err(e) => Err(e),
+            }
+        }
+        None => Err(Error::MissingObjectKey(".metadata.namespace")),
+    };
+    ctx.get_ref().metrics.reconciled(started.elapsed());
+    if let Err(e) = &result {
+        publish_event(
+            ctx.get_ref(),
+            &generator,
+            EventType::Warning,
+            "ReconcileFailed",
+            e.to_string(),
+        )
+        .await;
+    }
+
+    let mut failures = ctx.get_ref().failures.lock().unwrap();
+    match result {
+        Ok(action) => {
+            failures.succeeded(&key);
+            Ok(action)
+        }
+        Err(e) => Err(Error::Retrying {
+            attempts: failures.failed(key),
+            source: Box::new(e),
+        }),
+    }
+}
+
+/// Who owns the fields of everything the controller applies, see
+/// [`drifted_by`].
+const FIELD_MANAGER: &str = "podcleaner.kube-rt.shopvac.io";
+
+/// Label on every child object, naming the PodCleaner it belongs to, so
+/// children that are no longer wanted can be found and pruned.
+const OWNED_BY_LABEL: &str = "shopvac.io/owned-by";
+
+/// The recommended label marking everything the controller creates, cleanup
+/// jobs and their pods included, as shopvac's.
+const MANAGED_BY_LABEL: &str = "app.kubernetes.io/managed-by";
+
+/// Held on every PodCleaner until its CronJobs are stopped, so a deleted
+/// cleaner never gets to run one last time.
+const FINALIZER: &str = "shopvac.io/cleanup";
+
+/// How long a deleted PodCleaner waits for running cleanups before they are
+/// killed along with their CronJob.
+const CLEANUP_JOB_TIMEOUT: time::Duration = time::Duration::from_secs(5 * 60);
+
+/// How often a deleted PodCleaner looks again at the cleanups it waits for.
+const CLEANUP_JOB_POLL: time::Duration = time::Duration::from_secs(5);
+
+/// When a deleted PodCleaner stops waiting for its running cleanups, as RFC
+/// 3339. Kept on the object so the wait carries over requeues and restarts.
+const CLEANUP_DEADLINE_ANNOTATION: &str = "shopvac.io/cleanup-deadline";
+
+/// Suspends the CronJobs of a deleted PodCleaner and checks on the cleanups
+/// already running, which get `CLEANUP_JOB_TIMEOUT` from the first check to
+/// finish. Returns when to look again while any are left, `None` once the
+/// finalizer can be released.
+async fn drain_cleaner(generator: &PodCleaner, client: Client) -> Result<Option<Action>, Error> {
+    let target_namespace = generator
+        .metadata
+        .namespace
+        .as_ref()
+        .ok_or(Error::MissingObjectKey(".metadata.namespace"))?;
+    let cj_api = Api::<CronJob>::namespaced(client.clone(), target_namespace);
+    let names: Vec<String> = runs(generator)
+        .into_iter()
+        .map(|run| run.cron_job_name)
+        .collect();
+
+    for name in &names {
+        let suspended = cj_api
+            .patch(
+                name,
+                &PatchParams::default(),
+                &Patch::Merge(json!({ "spec": { "suspend": true } })),
+            )
+            .await;
+        match suspended {
+            Ok(_) => {}
+            Err(kube::Error::Api(e)) if e.code == 404 => {}
+            Err(e) => return Err(Error::CronJobDeletionFailed(e)),
+        }
+    }
+
+    let running = cleanup_jobs(client.clone(), target_namespace, &names)
+        .await
+        .map_err(Error::CronJobDeletionFailed)?
+        .into_iter()
+        .filter(|job| !finished(job))
+        .count();
+    if running == 0 {
+        return Ok(None);
+    }
+
+    let now = chrono::Utc::now();
+    let deadline = match cleanup_deadline(generator) {
+        Some(deadline) => deadline,
+        None => {
+            let deadline = now + chrono::Duration::from_std(CLEANUP_JOB_TIMEOUT).unwrap();
+            Api::<PodCleaner>::namespaced(client, target_namespace)
+                .patch(
+                    &generator.name(),
+                    &PatchParams::default(),
+                    &Patch::Merge(json!({
+                        "metadata": {
+                            "annotations": { CLEANUP_DEADLINE_ANNOTATION: deadline.to_rfc3339() }
+                        }
+                    })),
+                )
+                .await
+                .map_err(Error::CleanupDeadlinePatchFailed)?;
+            deadline
+        }
+    };
+    if now >= deadline {
+        tracing::warn!(
+            "{}/{}: killing {} cleanups still running after {:?}",
+            target_namespace,
+            generator.name(),
+            running,
+            CLEANUP_JOB_TIMEOUT
+        );
+        return Ok(None);
+    }
+    tracing::info!(
+        "{}/{}: waiting for {} running cleanups before deleting",
+        target_namespace,
+        generator.name(),
+        running
+    );
+    Ok(Some(Action::requeue(CLEANUP_JOB_POLL)))
+}
+
+/// The deadline `drain_cleaner` recorded on a deleted PodCleaner, if any.
+fn cleanup_deadline(generator: &PodCleaner) -> Option<chrono::DateTime<chrono::Utc>> {
+    let deadline = generator.annotations().get(CLEANUP_DEADLINE_ANNOTATION)?;
+    chrono::DateTime::parse_from_rfc3339(deadline)
+        .ok()
+        .map(|deadline| deadline.with_timezone(&chrono::Utc))
+}
+
+/// Deletes the CronJobs of a deleted PodCleaner in the foreground once
+/// `drain_cleaner` is done waiting, taking any job still running with them.
+/// The ServiceAccount, Role and RoleBinding provisioned for it go right
+/// after.
+async fn cleanup_cleaner(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Action, Error> {
+    let client = ctx.get_ref().client.clone();
+    let target_namespace = generator
+        .metadata
+        .namespace
+        .as_ref()
+        .ok_or(Error::MissingObjectKey(".metadata.namespace"))?;
+    let cj_api = Api::<CronJob>::namespaced(client.clone(), target_namespace);
+    let names: Vec<String> = runs(&generator)
+        .into_iter()
+        .map(|run| run.cron_job_name)
+        .collect();
+
+    for name in &names {
+        match cj_api.delete(name, &DeleteParams::foreground()).await {
+            Ok(_) => {}
+            Err(kube::Error::Api(e)) if e.code == 404 => {}
+            Err(e) => return Err(Error::CronJobDeletionFailed(e)),
+        }
+    }
+    // the garbage collector would get to these too, but only those that
+    // still have their owner reference, and on its own time
+    if !ctx.get_ref().skip_rbac {
+        prune_rbac(&generator, client, target_namespace, &[]).await;
+    }
+    tracing::info!(
+        "{}/{}: deleted its CronJobs and RBAC objects, releasing the finalizer",
+        target_namespace,
+        generator.name()
+    );
+    Ok(Action::await_change())
+}
+
+/// Consecutive reconcile failures per PodCleaner, the state `error_policy`
+/// backs off on.
+#[derive(Debug, Default)]
+struct Failures(HashMap<ObjectRef<PodCleaner>, u32>);
+
+impl Failures {
+    /// Forgets the failures of a PodCleaner that reconciled fine.
+    fn succeeded(&mut self, key: &ObjectRef<PodCleaner>) {
+        self.0.remove(key);
+    }
+
+    /// Counts a failure, returning how many there were in a row.
+    fn failed(&mut self, key: ObjectRef<PodCleaner>) -> u32 {
+        let attempts = self.0.entry(key).or_insert(0);
+        *attempts = attempts.saturating_add(1);
+        *attempts
+    }
+}
+
+async fn reconcile_cleaner(
+    generator: Arc<PodCleaner>,
+    ctx: Context<Data>,
+) -> Result<Action, Error> {
     let client = ctx.get_ref().client.clone();
     let target_namespace = generator
         .metadata
@@ -81,6 +671,345 @@ async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Act
         .as_ref()
         .ok_or(Error::MissingObjectKey(".metadata.namespace"))?;
 
+    let previous = generator.status.as_ref();
+    let pcs = Api::<PodCleaner>::namespaced(client.clone(), target_namespace);
+
+    // the CronJob controller would reject a bad schedule, and the cleaner a
+    // bad pattern, but only where the user never looks, so catch them here
+    let invalid = match (
+        validate_schedules(&generator.spec),
+        generator.spec.time_zone.as_deref().map(validate_time_zone),
+        validate_filters(&generator.spec),
+    ) {
+        (Err(e), _, _) => Some(("ScheduleInvalid", "InvalidCron", e)),
+        (Ok(()), Some(Err(e)), _) => Some(("ScheduleInvalid", "InvalidTimeZone", e)),
+        (_, _, Err((reason, e))) => Some(("FiltersInvalid", reason, e)),
+        _ => None,
+    };
+    if let Some((kind, reason, message)) = invalid {
+        tracing::warn!("{}/{}: {}", target_namespace, generator.name(), message);
+
+        publish_event(
+            ctx.get_ref(),
+            &generator,
+            EventType::Warning,
+            kind,
+            message.clone(),
+        )
+        .await;
+
+        let conditions = vec![
+            condition(previous, kind, true, reason, Some(message.clone())),
+            condition(previous, "Ready", false, kind, Some(message)),
+        ];
+        pcs.patch_status(
+            &generator.name(),
+            &PatchParams::default(),
+            &Patch::Merge(json!({
+                "status": {
+                    "observedGeneration": generator.metadata.generation,
+                    "conditions": conditions,
+                }
+            })),
+        )
+        .await
+        .map_err(Error::StatusPatchFailed)?;
+        // nothing changes until the spec is edited, which triggers a
+        // reconcile of its own
+        return Ok(Action::requeue(RECONCILE_PERMANENT_REQUEUE));
+    }
+
+    let manage_rbac = !ctx.get_ref().skip_rbac
+        && generator.spec.manage_rbac.unwrap_or(true)
+        && generator.spec.service_account_name.is_none();
+    let rbac = if manage_rbac {
+        provision_rbac(&generator, client.clone(), target_namespace).await
+    } else {
+        tracing::debug!("RBAC is managed externally, only applying the CronJob");
+        if !ctx.get_ref().skip_rbac {
+            prune_rbac(
+                &generator,
+                client.clone(),
+                target_namespace,
+                &[service_account_name(&generator)],
+            )
+            .await;
+        }
+        Ok(())
+    };
+    // older apiservers silently drop the field, which would quietly run the
+    // cleanups at the wrong time of day
+    let time_zone = generator.spec.time_zone.as_deref();
+    let time_zone_unsupported = time_zone.is_some() && !ctx.get_ref().supports_time_zone;
+    if time_zone_unsupported {
+        tracing::warn!(
+            "{}/{}: CronJob time zones need Kubernetes 1.25, leaving spec.time_zone out",
+            target_namespace,
+            generator.name()
+        );
+    }
+    let cron_job = match &rbac {
+        Ok(()) => Some(
+            apply_cron_job(
+                &generator,
+                client.clone(),
+                target_namespace,
+                &ctx.get_ref().default_image,
+                time_zone.filter(|_| !time_zone_unsupported),
+            )
+            .await,
+        ),
+        Err(_) => None,
+    };
+
+    // only on transitions, reconciles without changes stay quiet
+    if manage_rbac && rbac.is_ok() && !was_true(previous, "RBACProvisioned") {
+        publish_event(
+            ctx.get_ref(),
+            &generator,
+            EventType::Normal,
+            "RBACProvisioned",
+            format!(
+                "applied ServiceAccount {} and its Role and RoleBinding",
+                service_account_name(&generator)
+            ),
+        )
+        .await;
+    }
+    if let Some(Ok((_, repaired))) = &cron_job {
+        for repaired in repaired {
+            publish_event(
+                ctx.get_ref(),
+                &generator,
+                EventType::Warning,
+                "DriftRepaired",
+                format!("restored CronJob {}", repaired),
+            )
+            .await;
+        }
+        let reason = if !was_true(previous, "CronJobCreated") {
+            Some("CronJobCreated")
+        } else if generator.metadata.generation != previous.and_then(|s| s.observed_generation) {
+            Some("CronJobUpdated")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            publish_event(
+                ctx.get_ref(),
+                &generator,
+                EventType::Normal,
+                reason,
+                format!("applied CronJob {}", cron_job_name(&generator)),
+            )
+            .await;
+        }
+    }
+
+    let metrics = &ctx.get_ref().metrics;
+    for kind in [
+        rbac.as_ref().err(),
+        cron_job.as_ref().and_then(|cj| cj.as_ref().err()),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(Error::child_kind)
+    {
+        metrics.child_apply_failed(kind);
+    }
+
+    // report what happened before surfacing any error, so a broken cleaner
+    // shows up in `kubectl get` rather than only in the controller logs
+    let cj_status = cron_job
+        .as_ref()
+        .and_then(|cj| cj.as_ref().ok())
+        .and_then(|(cj, _)| cj.status.as_ref());
+    let rbac_condition = if manage_rbac {
+        applied(
+            previous,
+            "RBACProvisioned",
+            rbac.as_ref().map_err(ToString::to_string).map(|_| ()),
+        )
+    } else {
+        condition(previous, "RBACProvisioned", true, "ManagedExternally", None)
+    };
+    let cron_job_condition = applied(
+        previous,
+        "CronJobCreated",
+        match &cron_job {
+            Some(res) => res.as_ref().map(|_| ()).map_err(ToString::to_string),
+            None => Err("waiting for RBAC to be provisioned".to_string()),
+        },
+    );
+    // failed cleanups only show up on jobs the user never looks at, count
+    // each one once and say so on the PodCleaner
+    let last_run = match &cron_job {
+        Some(Ok(_)) => last_finished_job(
+            client.clone(),
+            target_namespace,
+            &runs(&generator)
+                .into_iter()
+                .map(|run| run.cron_job_name)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Unable to list the cleanup jobs: {}", e);
+            None
+        }),
+        _ => None,
+    };
+    let mut failed_runs = previous.and_then(|s| s.failed_runs).unwrap_or(0);
+    let mut last_failed_job = previous.and_then(|s| s.last_failed_job.clone());
+    let mut last_failed_run = previous.and_then(|s| s.last_failed_run.clone());
+    let last_run_condition = last_run.as_ref().map(|job| {
+        let failure = job_failure(job);
+        condition(
+            previous,
+            "LastRunSucceeded",
+            failure.is_none(),
+            if failure.is_some() {
+                "JobFailed"
+            } else {
+                "JobSucceeded"
+            },
+            failure.map(|(_, message)| message),
+        )
+    });
+    if let Some((job, (failed_at, message))) = last_run
+        .as_ref()
+        .and_then(|job| Some((job, job_failure(job)?)))
+    {
+        if last_failed_job.as_deref() != Some(job.name().as_str()) {
+            failed_runs += 1;
+            last_failed_job = Some(job.name());
+            last_failed_run = failed_at;
+            publish_event(
+                ctx.get_ref(),
+                &generator,
+                EventType::Warning,
+                "CleanupFailed",
+                format!("cleanup job {} failed: {}", job.name(), message),
+            )
+            .await;
+        }
+    }
+
+    let ready = applied(
+        previous,
+        "Ready",
+        if rbac_condition.status == "True" && cron_job_condition.status == "True" {
+            Ok(())
+        } else {
+            Err("not every child object could be applied".to_string())
+        },
+    );
+    let status = PodCleanerStatus {
+        observed_generation: generator.metadata.generation,
+        cron_job_name: Some(cron_job_name(&generator)),
+        last_schedule_time: cj_status
+            .and_then(|s| s.last_schedule_time.as_ref())
+            .map(|t| t.0.to_rfc3339()),
+        last_successful_run: cj_status
+            .and_then(|s| s.last_successful_time.as_ref())
+            .map(|t| t.0.to_rfc3339()),
+        last_failed_run,
+        last_failed_job,
+        failed_runs: Some(failed_runs),
+        conditions: vec![
+            condition(previous, "ScheduleInvalid", false, "ScheduleValid", None),
+            condition(previous, "FiltersInvalid", false, "FiltersValid", None),
+            condition(
+                previous,
+                "TimeZoneUnsupported",
+                time_zone_unsupported,
+                if time_zone_unsupported {
+                    "KubernetesTooOld"
+                } else {
+                    "TimeZoneApplied"
+                },
+                time_zone_unsupported.then(|| {
+                    "the cluster doesn't support spec.timeZone on CronJobs, the schedule runs \
+                     in the kube-controller-manager's time zone"
+                        .to_string()
+                }),
+            ),
+            condition(
+                previous,
+                "DryRun",
+                generator.spec.dry_run,
+                if generator.spec.dry_run {
+                    "ReportOnly"
+                } else {
+                    "Deleting"
+                },
+                None,
+            ),
+            condition(
+                previous,
+                "Suspended",
+                generator.spec.suspend,
+                if generator.spec.suspend {
+                    "SuspendedBySpec"
+                } else {
+                    "Scheduled"
+                },
+                None,
+            ),
+            rbac_condition,
+            cron_job_condition,
+        ]
+        .into_iter()
+        .chain(last_run_condition)
+        .chain([ready])
+        .collect(),
+    };
+    pcs.patch_status(
+        &generator.name(),
+        &PatchParams::default(),
+        &Patch::Merge(json!({ "status": status })),
+    )
+    .await
+    .map_err(Error::StatusPatchFailed)?;
+
+    rbac?;
+    if let Some(cron_job) = cron_job {
+        cron_job?;
+    }
+    Ok(Action::requeue(requeue_after(
+        &status,
+        ctx.get_ref().requeue_interval,
+    )))
+}
+
+/// `--requeue-interval` while the PodCleaner is healthy, sooner while it
+/// isn't Ready or its last run failed, to notice it recovering.
+fn requeue_after(status: &PodCleanerStatus, interval: time::Duration) -> time::Duration {
+    let unhealthy = status
+        .conditions
+        .iter()
+        .any(|c| matches!(c.type_.as_str(), "Ready" | "LastRunSucceeded") && c.status == "False");
+    if unhealthy {
+        interval.min(RECONCILE_UNHEALTHY_REQUEUE)
+    } else {
+        interval
+    }
+}
+
+/// Applies the ServiceAccount, Role and RoleBinding of one PodCleaner.
+///
+/// Every PodCleaner gets its own, named after it, so cleaners sharing a
+/// namespace don't take ownership from each other. The shared `shopvac`
+/// objects earlier versions created are left alone, once their CronJobs have
+/// moved over nothing uses them anymore and they go away with their owner.
+async fn provision_rbac(
+    generator: &PodCleaner,
+    client: Client,
+    target_namespace: &str,
+) -> Result<(), Error> {
+    let name = generator.name();
+    let role_name = format!("{}-shopvac-role", name);
+
     // first we must create a service account
     let sa_api = Api::<ServiceAccount>::namespaced(client.clone(), target_namespace);
     let sa: ServiceAccount = serde_json::from_value(json!({
@@ -89,7 +1018,9 @@ async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Act
         "metadata": {
             "ownerReferences": Some(vec![generator.controller_owner_ref(&()).unwrap()]),
             "namespace": target_namespace,
-            "name": "shopvac",
+            "name": service_account_name(generator),
+            "labels": child_labels(generator),
+            "annotations": child_annotations(generator),
         },
     }))
     .unwrap();
@@ -99,30 +1030,71 @@ async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Act
                 .name
                 .as_ref()
                 .ok_or(Error::MissingObjectKey(".metadata.name"))?,
-            &PatchParams::apply("podcleaner.kube-rt.shopvac.io"),
+            &PatchParams::apply(FIELD_MANAGER),
             &Patch::Apply(&sa),
         )
         .await
-        .map_err(Error::CronJobCreationFailed)?;
+        .map_err(Error::ServiceAccountCreationFailed)?;
+
+    // NEXT WE MUST DO RBAC, starting with the role the cleaner binds to
+    let role: Role = serde_json::from_value(json!({
+        "apiVersion": "rbac.authorization.k8s.io/v1",
+        "kind": "Role",
+        "metadata": {
+            "name": role_name,
+            "ownerReferences": Some(vec![generator.controller_owner_ref(&()).unwrap()]),
+            "namespace": target_namespace,
+            "labels": child_labels(generator),
+            "annotations": child_annotations(generator),
+        },
+        "rules": [
+            {
+                "apiGroups": [""],
+                "resources": ["pods"],
+                "verbs": ["list", "get", "delete"]
+            },
+            {
+                "apiGroups": [""],
+                "resources": ["configmaps"],
+                "verbs": ["get", "create", "update", "patch"]
+            }
+        ]
+    }))
+    .unwrap();
+
+    let role_api = Api::<Role>::namespaced(client.clone(), target_namespace);
+    role_api
+        .patch(
+            role.metadata
+                .name
+                .as_ref()
+                .ok_or(Error::MissingObjectKey(".metadata.name"))?,
+            &PatchParams::apply(FIELD_MANAGER),
+            &Patch::Apply(&role),
+        )
+        .await
+        .map_err(Error::RoleCreationFailed)?;
 
-    // NEXT WE MUST DO RBAC
+    let rb_name = format!("{}-shopvac-rb", name);
     let rb: RoleBinding = serde_json::from_value(json!({
         "apiVersion": "rbac.authorization.k8s.io/v1",
         "kind": "RoleBinding",
         "metadata": {
-            "name": "shopvac-delete-rb",
+            "name": rb_name,
             "ownerReferences": Some(vec![generator.controller_owner_ref(&()).unwrap()]),
             "namespace":  target_namespace,
+            "labels": child_labels(generator),
+            "annotations": child_annotations(generator),
         },
         "roleRef": {
             "apiGroup": "rbac.authorization.k8s.io",
-            "kind": "ClusterRole",
-            "name": "shopvac-pod-deletion-role"
+            "kind": "Role",
+            "name": role_name
         },
         "subjects": [
             {
                 "kind": "ServiceAccount",
-                "name": "shopvac"
+                "name": service_account_name(generator)
             }
         ]
     }))
@@ -137,19 +1109,394 @@ async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Act
                 .name
                 .as_ref()
                 .ok_or(Error::MissingObjectKey(".metadata.name"))?,
-            &PatchParams::apply("podcleaner.kube-rt.shopvac.io"),
+            &PatchParams::apply(FIELD_MANAGER),
             &Patch::Apply(&rb),
         )
         .await
-        .map_err(Error::CronJobCreationFailed)?;
+        .map_err(Error::RoleBindingCreationFailed)?;
+
+    prune(&sa_api, generator, &[service_account_name(generator)]).await;
+    prune(&role_api, generator, &[role_name]).await;
+    prune(&rb_api, generator, &[rb_name]).await;
+    Ok(())
+}
+
+/// Deletes the RBAC objects provisioned for the PodCleaner, except the
+/// ServiceAccounts named in `keep_service_accounts`: when its ServiceAccount
+/// became managed elsewhere the one it now names, in case that is one shopvac
+/// created, and nothing once the PodCleaner is deleted.
+async fn prune_rbac(
+    generator: &PodCleaner,
+    client: Client,
+    target_namespace: &str,
+    keep_service_accounts: &[String],
+) {
+    prune(
+        &Api::<ServiceAccount>::namespaced(client.clone(), target_namespace),
+        generator,
+        keep_service_accounts,
+    )
+    .await;
+    prune(
+        &Api::<Role>::namespaced(client.clone(), target_namespace),
+        generator,
+        &[],
+    )
+    .await;
+    prune(
+        &Api::<RoleBinding>::namespaced(client, target_namespace),
+        generator,
+        &[],
+    )
+    .await;
+}
+
+/// Deletes the PodCleaner's children of kind `K`, found by their
+/// `OWNED_BY_LABEL`, that aren't named in `keep`: left behind by a rename, a
+/// removed schedule or a change in naming. Best effort, whatever is left
+/// still goes away with the PodCleaner.
+async fn prune<K>(api: &Api<K>, generator: &PodCleaner, keep: &[String])
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+    K::DynamicType: Default,
+{
+    let kind = K::kind(&K::DynamicType::default()).to_string();
+    let lp = ListParams::default().labels(&format!("{}={}", OWNED_BY_LABEL, generator.name()));
+    let existing = match api.list(&lp).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            tracing::warn!("Unable to list {}s to prune: {}", kind, e);
+            return;
+        }
+    };
+    for obj in existing.iter().filter(|obj| !keep.contains(&obj.name())) {
+        tracing::info!("Deleting stale {} {}", kind, obj.name());
+        if let Err(e) = api.delete(&obj.name(), &DeleteParams::default()).await {
+            tracing::warn!("Unable to delete {} {}: {}", kind, obj.name(), e);
+        }
+    }
+}
+
+/// The PodCleaner's own labels, so a --selector picks out its children along
+/// with it, `MANAGED_BY_LABEL` and `OWNED_BY_LABEL`.
+fn child_labels(generator: &PodCleaner) -> BTreeMap<String, String> {
+    let mut labels = generator.labels().clone();
+    labels.insert(MANAGED_BY_LABEL.to_string(), "shopvac".to_string());
+    labels.insert(OWNED_BY_LABEL.to_string(), generator.name());
+    labels
+}
+
+/// The PodCleaner's own annotations named in `propagate_annotations`, if
+/// there are any.
+fn child_annotations(generator: &PodCleaner) -> Option<BTreeMap<String, String>> {
+    let annotations = propagated(
+        generator.annotations(),
+        &generator.spec.propagate_annotations,
+    );
+    (!annotations.is_empty()).then(|| annotations)
+}
+
+/// The entries of `from` whose key is listed in `keys`.
+fn propagated(
+    from: &BTreeMap<String, String>,
+    keys: &Option<Vec<String>>,
+) -> BTreeMap<String, String> {
+    keys.iter()
+        .flatten()
+        .filter_map(|key| Some((key.clone(), from.get(key)?.clone())))
+        .collect()
+}
+
+/// Labels of the cleanup jobs: the propagated ones, `MANAGED_BY_LABEL` and
+/// then `job_labels`.
+fn job_labels(generator: &PodCleaner) -> BTreeMap<String, String> {
+    let mut labels = propagated(generator.labels(), &generator.spec.propagate_labels);
+    labels.insert(MANAGED_BY_LABEL.to_string(), "shopvac".to_string());
+    labels.extend(generator.spec.job_labels.clone().unwrap_or_default());
+    labels
+}
+
+/// Annotations of the cleanup jobs, the propagated ones and then
+/// `job_annotations`, if there are any.
+fn job_annotations(generator: &PodCleaner) -> Option<BTreeMap<String, String>> {
+    let mut annotations = child_annotations(generator).unwrap_or_default();
+    annotations.extend(generator.spec.job_annotations.clone().unwrap_or_default());
+    (!annotations.is_empty()).then(|| annotations)
+}
+
+/// The ServiceAccount the cleanup job runs as.
+fn service_account_name(generator: &PodCleaner) -> String {
+    generator
+        .spec
+        .service_account_name
+        .clone()
+        .unwrap_or_else(|| format!("{}-shopvac-sa", generator.name()))
+}
+
+fn cron_job_name(generator: &PodCleaner) -> String {
+    format!("{name}-clean-job", name = generator.name())
+}
+
+/// One scheduled run of a PodCleaner's cleanup, each has a CronJob of its
+/// own.
+struct Run<'a> {
+    cron_job_name: String,
+    report_name: String,
+    schedule: &'a str,
+    delete_older_than: u32,
+}
+
+/// The run for `schedule`, followed by the ones for `schedules`.
+fn runs(generator: &PodCleaner) -> Vec<Run<'_>> {
+    let name = generator.name();
+    let main = Run {
+        cron_job_name: cron_job_name(generator),
+        report_name: format!("{}-report", name),
+        schedule: &generator.spec.schedule,
+        delete_older_than: generator.spec.delete_older_than,
+    };
+    std::iter::once(main)
+        .chain(generator.spec.schedules.iter().flatten().map(|extra| Run {
+            cron_job_name: format!("{}-{}", cron_job_name(generator), extra.name),
+            report_name: format!("{}-{}-report", name, extra.name),
+            schedule: &extra.schedule,
+            delete_older_than: extra.delete_older_than,
+        }))
+        .collect()
+}
+
+/// The jobs any of the CronJobs started that are still around.
+async fn cleanup_jobs(
+    client: Client,
+    namespace: &str,
+    cron_job_names: &[String],
+) -> Result<Vec<Job>, kube::Error> {
+    let jobs = Api::<Job>::namespaced(client, namespace)
+        .list(&ListParams::default())
+        .await?;
+    Ok(jobs
+        .items
+        .into_iter()
+        .filter(|job| {
+            job.owner_references()
+                .iter()
+                .any(|o| o.kind == "CronJob" && cron_job_names.contains(&o.name))
+        })
+        .collect())
+}
+
+/// Whether the job completed or failed, rather than still running.
+fn finished(job: &Job) -> bool {
+    job.status
+        .iter()
+        .flat_map(|s| s.conditions.iter().flatten())
+        .any(|c| (c.type_ == "Complete" || c.type_ == "Failed") && c.status == "True")
+}
+
+/// The most recently finished job any of the CronJobs started, if any is
+/// left.
+async fn last_finished_job(
+    client: Client,
+    namespace: &str,
+    cron_job_names: &[String],
+) -> Result<Option<Job>, kube::Error> {
+    Ok(cleanup_jobs(client, namespace, cron_job_names)
+        .await?
+        .into_iter()
+        .filter(finished)
+        .max_by_key(|job| job.metadata.creation_timestamp.clone()))
+}
+
+/// When and why a finished job failed, `None` when it succeeded.
+fn job_failure(job: &Job) -> Option<(Option<String>, String)> {
+    let failed = job
+        .status
+        .iter()
+        .flat_map(|s| s.conditions.iter().flatten())
+        .find(|c| c.type_ == "Failed" && c.status == "True")?;
+    let message = match (&failed.reason, &failed.message) {
+        (Some(reason), Some(message)) => format!("{}: {}", reason, message),
+        (Some(text), None) | (None, Some(text)) => text.clone(),
+        (None, None) => "the job failed".to_string(),
+    };
+    Some((
+        failed
+            .last_transition_time
+            .as_ref()
+            .map(|t| t.0.to_rfc3339()),
+        message,
+    ))
+}
+
+/// Publishes an Event on the PodCleaner, so `kubectl describe` tells what
+/// the controller did with it. Failing to do so is only logged.
+///
+/// Needs `create` and `patch` on `events.k8s.io` events in the PodCleaner's
+/// namespace, see the CSV's cluster permissions.
+async fn publish_event(
+    data: &Data,
+    generator: &PodCleaner,
+    type_: EventType,
+    reason: &str,
+    note: String,
+) {
+    let recorder = Recorder::new(
+        data.client.clone(),
+        data.reporter.clone(),
+        generator.object_ref(&()),
+    );
+    let event = Event {
+        type_,
+        reason: reason.to_string(),
+        note: Some(note),
+        action: "Reconciling".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        tracing::warn!("Unable to publish event: {}", e);
+    }
+}
+
+/// Applies the CronJobs running the cleanup, returning the one for
+/// `schedule` as the apiserver has it, status included, and the CronJobs
+/// that had been edited by someone else and were restored.
+///
+/// The apply is authoritative: conflicts are forced, and the jobTemplate of a
+/// CronJob someone else changed is replaced wholesale first, as applying
+/// alone would keep fields others added.
+///
+/// The PodCleaner's other CronJobs, e.g. of extra schedules that were since
+/// removed from the spec, are deleted.
+async fn apply_cron_job(
+    generator: &PodCleaner,
+    client: Client,
+    target_namespace: &str,
+    default_image: &str,
+    time_zone: Option<&str>,
+) -> Result<(CronJob, Vec<String>), Error> {
+    let cj_api = Api::<CronJob>::namespaced(client.clone(), target_namespace);
+    let runs = runs(generator);
+
+    let mut main = None;
+    let mut repaired = Vec::new();
+    for run in &runs {
+        let cj = cron_job_for(generator, target_namespace, default_image, run);
+        tracing::debug!("\n{}", serde_yaml::to_string(&cj).unwrap());
+
+        // k8s-openapi's CronJobSpec predates `timeZone`, so it is added to the
+        // applied object by hand
+        let mut applied = serde_json::to_value(&cj).expect("Failed to serialize CronJob");
+        if let Some(tz) = time_zone {
+            applied["spec"]["timeZone"] = json!(tz);
+        }
+
+        let existing = match cj_api.get(&run.cron_job_name).await {
+            Ok(existing) => Some(existing),
+            Err(kube::Error::Api(e)) if e.code == 404 => None,
+            Err(e) => return Err(Error::CronJobCreationFailed(e)),
+        };
+        let managers = existing
+            .as_ref()
+            .map(|cj| drifted_by(cj, &applied))
+            .unwrap_or_default();
+        if !managers.is_empty() {
+            tracing::info!(
+                "CronJob {} was changed by {}, restoring it",
+                run.cron_job_name,
+                managers.join(", ")
+            );
+            let mut template = applied["spec"]["jobTemplate"].clone();
+            template["$patch"] = json!("replace");
+            cj_api
+                .patch(
+                    &run.cron_job_name,
+                    &PatchParams {
+                        field_manager: Some(FIELD_MANAGER.to_string()),
+                        ..PatchParams::default()
+                    },
+                    &Patch::Strategic(json!({ "spec": { "jobTemplate": template } })),
+                )
+                .await
+                .map_err(Error::CronJobCreationFailed)?;
+            repaired.push(format!(
+                "{} (changed by {})",
+                run.cron_job_name,
+                managers.join(", ")
+            ));
+        }
+
+        let cj = cj_api
+            .patch(
+                &run.cron_job_name,
+                &PatchParams::apply(FIELD_MANAGER).force(),
+                &Patch::Apply(&applied),
+            )
+            .await
+            .map_err(Error::CronJobCreationFailed)?;
+        main.get_or_insert(cj);
+    }
+
+    let names: Vec<String> = runs.into_iter().map(|run| run.cron_job_name).collect();
+    prune(&cj_api, generator, &names).await;
 
-    // CRON JOB PART
+    Ok((main.expect("the main schedule always has a run"), repaired))
+}
+
+/// The field managers other than the controller's own that own parts of the
+/// CronJob the controller is authoritative for: anything in its jobTemplate,
+/// and the other spec fields `desired` sets. Status updates and fields the
+/// controller leaves alone don't count.
+fn drifted_by(cj: &CronJob, desired: &serde_json::Value) -> Vec<String> {
+    let mut managers: Vec<String> = cj
+        .metadata
+        .managed_fields
+        .iter()
+        .flatten()
+        .filter(|entry| entry.subresource.as_deref().unwrap_or_default().is_empty())
+        .filter(|entry| {
+            let spec = entry.fields_v1.as_ref().map(|fields| &fields.0["f:spec"]);
+            spec.and_then(serde_json::Value::as_object)
+                .map_or(false, |spec| {
+                    spec.keys().any(|field| {
+                        field == "f:jobTemplate"
+                            || field
+                                .strip_prefix("f:")
+                                .map_or(false, |field| desired["spec"].get(field).is_some())
+                    })
+                })
+        })
+        .filter_map(|entry| entry.manager.clone())
+        .filter(|manager| manager != FIELD_MANAGER)
+        .collect();
+    managers.sort();
+    managers.dedup();
+    managers
+}
+
+/// The CronJob running the cleanup on the PodCleaner's main schedule.
+fn cron_job(generator: &PodCleaner, target_namespace: &str, default_image: &str) -> CronJob {
+    let runs = runs(generator);
+    cron_job_for(generator, target_namespace, default_image, &runs[0])
+}
+
+/// The CronJob running the cleanup for one of the PodCleaner's runs.
+fn cron_job_for(
+    generator: &PodCleaner,
+    target_namespace: &str,
+    default_image: &str,
+    run: &Run,
+) -> CronJob {
     // build up our args to pass to the cleaner binary
     let mut args: Vec<String> = Vec::new();
-    args.push("--actually-delete".to_string());
+    args.push("clean".to_string());
+    if !generator.spec.dry_run {
+        args.push("--actually-delete".to_string());
+    }
     // add the namespace we are currently in
     args.push("-n".to_string());
     args.push(target_namespace.to_string());
+    // the RBAC was only just applied, fail the run early if it isn't in effect
+    args.push("--health-check".to_string());
     // add label selectors
     if let Some(ls) = &generator.spec.label_selector {
         args.push("-l".to_string());
@@ -160,25 +1507,109 @@ async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Act
         args.push("-f".to_string());
         args.push(fs.to_string())
     }
+    if let Some(phases) = generator.spec.pod_phases.as_ref().filter(|p| !p.is_empty()) {
+        let phases: Vec<String> = phases.iter().map(|p| format!("{:?}", p)).collect();
+        args.push("--phase".to_string());
+        args.push(phases.join(","));
+    }
+    if let Some(pattern) = &generator.spec.exclude_pod_pattern {
+        args.push("--exclude-pod-pattern".to_string());
+        args.push(pattern.to_string());
+    }
+    if let Some(key) = &generator.spec.protection_annotation {
+        args.push("--protection-annotation".to_string());
+        args.push(key.to_string());
+    }
+    if let Some(kinds) = generator
+        .spec
+        .owner_kinds
+        .as_ref()
+        .filter(|k| !k.is_empty())
+    {
+        args.push("--owner-kind".to_string());
+        args.push(kinds.join(","));
+    }
+    if generator.spec.naked_only.unwrap_or(false) {
+        args.push("--naked-only".to_string());
+    }
+    if let Some(max) = generator.spec.max_delete {
+        args.push("--max-pods".to_string());
+        args.push(max.to_string());
+    }
 
     args.push("--older-than".to_string());
-    args.push(generator.spec.delete_older_than.to_string());
+    args.push(run.delete_older_than.to_string());
+    // keep a summary of each run around after the job pod is gone
+    args.push("--report-configmap".to_string());
+    args.push(run.report_name.clone());
+    if let Some(uid) = generator.uid() {
+        args.push("--report-owner-name".to_string());
+        args.push(generator.name());
+        args.push("--report-owner-uid".to_string());
+        args.push(uid);
+    }
+    if let Some(url) = &generator.spec.notify_url {
+        args.push("--notify-webhook".to_string());
+        args.push(url.to_string());
+    }
     tracing::debug!("args: {:?}", args);
 
+    let mut pod_labels = job_labels(generator);
+    pod_labels.extend(generator.spec.pod_labels.clone().unwrap_or_default());
+    let mut pod_annotations = job_annotations(generator).unwrap_or_default();
+    pod_annotations.extend(generator.spec.pod_annotations.clone().unwrap_or_default());
+
     let cjs: CronJobSpec = serde_json::from_value(json!({
-        "schedule": generator.spec.schedule,
-        "concurrencyPolicy": "Forbid",
-        "failedJobsHistoryLimit": 1,
-        "successfulJobsHistoryLimit": 1,
+        "schedule": run.schedule,
+        "concurrencyPolicy": generator.spec.concurrency_policy,
+        "startingDeadlineSeconds": generator.spec.starting_deadline_seconds,
+        "suspend": generator.spec.suspend,
+        "failedJobsHistoryLimit": generator.spec.failed_jobs_history_limit.unwrap_or(1),
+        "successfulJobsHistoryLimit": generator.spec.successful_jobs_history_limit.unwrap_or(1),
         "jobTemplate": {
+            "metadata": {
+                "labels": job_labels(generator),
+                "annotations": job_annotations(generator),
+            },
             "spec":{
+                "backoffLimit": generator.spec.backoff_limit.unwrap_or(2),
+                "activeDeadlineSeconds": generator.spec.active_deadline_seconds.unwrap_or(900),
+                // the cleaner shouldn't leave pods of its own behind
+                "ttlSecondsAfterFinished": generator
+                    .spec
+                    .ttl_seconds_after_finished
+                    .unwrap_or(24 * 60 * 60),
                 "template": {
+                    "metadata": {
+                        "labels": pod_labels,
+                        "annotations": (!pod_annotations.is_empty()).then(|| pod_annotations),
+                    },
                     "spec": {
-                        "serviceAccountName": "shopvac",
+                        "securityContext": generator
+                            .spec
+                            .pod_security_context
+                            .clone()
+                            .unwrap_or_else(restricted_pod_security_context),
+                        "serviceAccountName": service_account_name(generator),
                         "restartPolicy": "Never",
+                        "nodeSelector": generator.spec.node_selector,
+                        "tolerations": generator.spec.tolerations,
+                        "affinity": generator.spec.affinity,
+                        "priorityClassName": generator.spec.priority_class_name,
                         "containers": [{
                         "name": "pod-delete",
-                        "image": "quay.io/wseaton/shopvac:latest",
+                        "image": generator
+                            .spec
+                            .image
+                            .as_deref()
+                            .unwrap_or(default_image),
+                        "imagePullPolicy": generator.spec.image_pull_policy,
+                        "resources": generator.spec.resources,
+                        "securityContext": generator
+                            .spec
+                            .security_context
+                            .clone()
+                            .unwrap_or_else(restricted_security_context),
                         "args": args
                         }],
                     }
@@ -188,99 +1619,605 @@ async fn reconcile(generator: Arc<PodCleaner>, ctx: Context<Data>) -> Result<Act
     }))
     .expect("Failed to generate CronJobSpec");
 
-    let cj = CronJob {
+    CronJob {
         metadata: ObjectMeta {
-            name: Some(format!(
-                "{name}-clean-job",
-                name = generator.metadata.name.clone().unwrap()
-            )),
+            name: Some(run.cron_job_name.clone()),
             namespace: generator.metadata.namespace.clone(),
+            labels: Some(child_labels(generator)),
+            annotations: child_annotations(generator),
             owner_references: Some(vec![generator.controller_owner_ref(&()).unwrap()]),
             ..ObjectMeta::default()
         },
         spec: Some(cjs),
         ..Default::default()
-    };
-
-    tracing::debug!("\n{}", serde_yaml::to_string(&cj).unwrap());
-
-    let cj_api = Api::<CronJob>::namespaced(client.clone(), target_namespace);
+    }
+}
 
-    cj_api
-        .patch(
-            cj.metadata
-                .name
-                .as_ref()
-                .ok_or(Error::MissingObjectKey(".metadata.name"))?,
-            &PatchParams::apply("podcleaner.kube-rt.shopvac.io"),
-            &Patch::Apply(&cj),
-        )
-        .await
-        .map_err(Error::CronJobCreationFailed)?;
-    Ok(Action::requeue(tokio::time::Duration::from_secs(300)))
+/// Pod security defaults that satisfy the restricted PodSecurity profile.
+fn restricted_pod_security_context() -> PodSecurityContext {
+    serde_json::from_value(json!({
+        "runAsNonRoot": true,
+        "runAsUser": 65534,
+        "seccompProfile": { "type": "RuntimeDefault" },
+    }))
+    .expect("Failed to generate PodSecurityContext")
+}
+
+/// Container security defaults that satisfy the restricted PodSecurity
+/// profile, the cleaner only talks to the apiserver.
+fn restricted_security_context() -> SecurityContext {
+    serde_json::from_value(json!({
+        "allowPrivilegeEscalation": false,
+        "readOnlyRootFilesystem": true,
+        "runAsNonRoot": true,
+        "capabilities": { "drop": ["ALL"] },
+    }))
+    .expect("Failed to generate SecurityContext")
+}
+
+/// Checks the main schedule and every extra one, whose names also have to
+/// be unique.
+fn validate_schedules(spec: &PodCleanerSpec) -> Result<(), String> {
+    let extra = spec.schedules.iter().flatten();
+    for schedule in std::iter::once(&spec.schedule).chain(extra.clone().map(|e| &e.schedule)) {
+        validate_schedule(schedule)
+            .map_err(|e| format!("invalid schedule {:?}: {}", schedule, e))?;
+    }
+    let mut names = HashSet::new();
+    match extra.map(|e| &e.name).find(|name| !names.insert(*name)) {
+        Some(name) => Err(format!("schedule name {:?} is used more than once", name)),
+        None => Ok(()),
+    }
+}
+
+/// Checks the pod filters the cleaner would reject, returning the condition
+/// reason and message for the first problem.
+fn validate_filters(spec: &PodCleanerSpec) -> Result<(), (&'static str, String)> {
+    if let Some(Err(e)) = spec.exclude_pod_pattern.as_deref().map(regex::Regex::new) {
+        return Err((
+            "InvalidPattern",
+            format!("invalid exclude_pod_pattern: {}", e),
+        ));
+    }
+    let owner_kinds = spec.owner_kinds.as_ref().map_or(false, |k| !k.is_empty());
+    if spec.naked_only.unwrap_or(false) && owner_kinds {
+        return Err((
+            "ConflictingOwners",
+            "naked_only selects pods without owners, it can't be combined with owner_kinds"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks a CronJob schedule: five cron fields, or a macro such as `@hourly`.
+fn validate_schedule(schedule: &str) -> Result<(), String> {
+    Cron::new(schedule)
+        .parse()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Checks a time zone against the IANA database, the same one the CronJob
+/// controller uses.
+fn validate_time_zone(tz: &str) -> Result<(), String> {
+    tz.parse::<chrono_tz::Tz>()
+        .map(|_| ())
+        .map_err(|_| format!("unknown time zone {:?}", tz))
+}
+
+/// Whether the apiserver keeps `spec.timeZone` on CronJobs, beta and on by
+/// default since 1.25.
+fn supports_time_zone(version: &Info) -> bool {
+    let number = |s: &str| -> u32 {
+        s.trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse()
+            .unwrap_or(0)
+    };
+    (number(&version.major), number(&version.minor)) >= (1, 25)
+}
+
+/// A condition that is true when `result` is ok.
+fn applied(
+    previous: Option<&PodCleanerStatus>,
+    type_: &str,
+    result: Result<(), String>,
+) -> PodCleanerCondition {
+    match result {
+        Ok(()) => condition(previous, type_, true, type_, None),
+        Err(e) => condition(previous, type_, false, "Failed", Some(e)),
+    }
+}
+
+/// Whether `previous` has the condition, set to true.
+fn was_true(previous: Option<&PodCleanerStatus>, type_: &str) -> bool {
+    previous
+        .into_iter()
+        .flat_map(|s| &s.conditions)
+        .any(|c| c.type_ == type_ && c.status == "True")
+}
+
+/// A status condition, carrying over the transition time from `previous` when
+/// the status didn't change.
+fn condition(
+    previous: Option<&PodCleanerStatus>,
+    type_: &str,
+    status: bool,
+    reason: &str,
+    message: Option<String>,
+) -> PodCleanerCondition {
+    let status = if status { "True" } else { "False" };
+    let last_transition_time = previous
+        .into_iter()
+        .flat_map(|s| &s.conditions)
+        .find(|c| c.type_ == type_ && c.status == status)
+        .map(|c| c.last_transition_time.clone())
+        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+    PodCleanerCondition {
+        type_: type_.to_string(),
+        status: status.to_string(),
+        reason: reason.to_string(),
+        message,
+        last_transition_time,
+    }
+}
+
+/// Server-side applies the PodCleaner CRD and waits until the apiserver
+/// serves it.
+async fn install_crds(client: Client, deadline: time::Instant) -> Result<()> {
+    let crds: Api<CustomResourceDefinition> = Api::all(client);
+    let crd = PodCleaner::crd();
+    let name = crd.name();
+    match crds
+        .patch(
+            &name,
+            &PatchParams::apply(FIELD_MANAGER).force(),
+            &Patch::Apply(&crd),
+        )
+        .await
+    {
+        Ok(_) => {}
+        Err(kube::Error::Api(e)) if e.code == 403 => bail!(
+            "Not allowed to install CRD {}, apply it with `shopvac-controller crd | kubectl apply -f -` \
+             as a cluster admin or grant patch on customresourcedefinitions: {}",
+            name,
+            e.message
+        ),
+        Err(e) => return Err(anyhow!("Failed to install CRD {}: {}", name, e)),
+    }
+    time::timeout_at(
+        deadline,
+        await_condition(crds, &name, conditions::is_crd_established()),
+    )
+    .await
+    .map_err(|_| anyhow!("Timed out waiting for CRD {} to be established", name))??;
+    tracing::info!("CRD {} is established", name);
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Use this to bootstrap the CR for dev purposes.
-    // println!("{}", serde_yaml::to_string(&PodCleaner::crd()).unwrap());
-
     let Args {
+        command,
         log_level,
         log_format,
         client,
         admin,
-        exit: _,
+        exit,
         timeout: Timeout(timeout),
-        selector: _,
+        selector,
+        namespace,
+        skip_rbac,
+        default_image,
+        metrics_addr,
+        unhealthy_after: Timeout(unhealthy_after),
+        ensure_crds,
+        requeue_interval: RequeueInterval(requeue_interval),
+        error_backoff: Timeout(error_backoff),
     } = Args::parse();
+    if error_backoff.is_zero() {
+        bail!("--error-backoff must be longer than 0");
+    }
 
     let deadline = time::Instant::now() + timeout;
 
+    match command {
+        Some(Command::Crd { install: false }) => {
+            print!("{}", serde_yaml::to_string(&PodCleaner::crd())?);
+            return Ok(());
+        }
+        Some(Command::Crd { install: true }) => {
+            let client = match time::timeout_at(deadline, client.try_client()).await {
+                Ok(res) => res?,
+                Err(_) => bail!("Timed out waiting for Kubernetes client to initialize"),
+            };
+            install_crds(client, deadline).await?;
+            println!("CRD {} is established", PodCleaner::crd().name());
+            return Ok(());
+        }
+        None => {}
+    }
+
     // Configure a runtime with:
     // - a Kubernetes client
-    // - an admin server with /live and /ready endpoints
+    // - an admin server with /live and /ready endpoints, ready once the
+    //   PodCleaners could be listed
     // - a tracing (logging) subscriber
     let rt = kubert::Runtime::builder()
         .with_log(log_level, log_format)
         .with_admin(admin)
         .with_client(client);
-    let runtime = match time::timeout_at(deadline, rt.build()).await {
+    let mut runtime = match time::timeout_at(deadline, rt.build()).await {
         Ok(res) => res?,
         Err(_) => bail!("Timed out waiting for Kubernetes client to initialize"),
     };
 
-    let pcs = Api::<PodCleaner>::all(runtime.client());
-    let cj: Api<CronJob> = Api::<CronJob>::all(runtime.client());
-
-    Controller::new(pcs, ListParams::default())
-        .owns(cj, ListParams::default())
-        .shutdown_on_signal()
-        .run(
-            reconcile,
-            error_policy,
-            Context::new(Data {
-                client: runtime.client().clone(),
-            }),
-        )
+    if ensure_crds {
+        install_crds(runtime.client(), deadline).await?;
+    }
+
+    let supports_time_zone = match runtime.client().apiserver_version().await {
+        Ok(version) => supports_time_zone(&version),
+        Err(e) => {
+            tracing::warn!(
+                "Unable to read the Kubernetes version, assuming no CronJob time zones: {}",
+                e
+            );
+            false
+        }
+    };
+
+    let (pcs, cj) = match &namespace {
+        Some(ns) => {
+            tracing::info!("Watching PodCleaners in namespace {}", ns);
+            (
+                Api::<PodCleaner>::namespaced(runtime.client(), ns),
+                Api::<CronJob>::namespaced(runtime.client(), ns),
+            )
+        }
+        None => (
+            Api::<PodCleaner>::all(runtime.client()),
+            Api::<CronJob>::all(runtime.client()),
+        ),
+    };
+
+    // e.g. the CRD isn't installed or RBAC is missing, the watch would fail
+    // the same way, so stay unready until a list goes through
+    let initialized = runtime.initialized_handle();
+    let initial_list = pcs.clone();
+    let lp = watch_params(selector.as_deref()).limit(1);
+    tokio::spawn(async move {
+        let mut failures = 0u32;
+        while let Err(e) = initial_list.list(&lp).await {
+            failures += 1;
+            let delay = WATCH_BACKOFF_MAX.min(time::Duration::from_secs(1 << failures.min(6)));
+            tracing::warn!("Unable to list PodCleaners, retrying in {:?}: {}", delay, e);
+            time::sleep(delay).await;
+        }
+        tracing::info!("Listed PodCleaners, ready");
+        drop(initialized);
+    });
+
+    // with --exit, the PodCleaners to see reconciled before stopping
+    let one_shot = if exit {
+        let existing =
+            match time::timeout_at(deadline, pcs.list(&watch_params(selector.as_deref()))).await {
+                Ok(res) => res?,
+                Err(_) => bail!("Timed out listing the PodCleaners to reconcile"),
+            };
+        Some(OneShot::new(
+            existing.iter().map(|pc| (pc.namespace(), pc.name())),
+        ))
+    } else {
+        None
+    };
+
+    // what `Controller::new(pcs, ..).owns(cj, ..)` sets up, but with the
+    // watches backing off after errors, which they don't do on their own
+    let metrics = Arc::new(Metrics::default());
+    let writer = Writer::<PodCleaner>::default();
+    let store = writer.as_reader();
+    let cleaners = trigger_self(
+        try_flatten_applied(backed_off(
+            "PodCleaner",
+            reflector(writer, watcher(pcs, watch_params(selector.as_deref()))),
+            metrics.clone(),
+            WATCH_BACKOFF_MAX,
+        )),
+        (),
+    );
+    let cron_jobs = trigger_owners(
+        try_flatten_touched(backed_off(
+            "CronJob",
+            watcher(cj, watch_params(selector.as_deref())),
+            metrics.clone(),
+            WATCH_BACKOFF_MAX,
+        )),
+        (),
+        (),
+    );
+    let metrics_server = metrics::serve(
+        metrics_addr,
+        metrics.clone(),
+        store.clone(),
+        unhealthy_after,
+    )
+    .map_err(|e| anyhow!("Unable to serve metrics on {}: {}", metrics_addr, e))?;
+    tokio::spawn(metrics_server);
+
+    let results = applier(
+        reconcile,
+        error_policy,
+        Context::new(Data {
+            client: runtime.client().clone(),
+            failures: Mutex::default(),
+            skip_rbac,
+            default_image,
+            supports_time_zone,
+            reporter: Reporter {
+                controller: "shopvac-controller".to_string(),
+                // the pod name, to tell replicas apart
+                instance: std::env::var("HOSTNAME").ok(),
+            },
+            metrics: metrics.clone(),
+            requeue_interval,
+            error_backoff,
+        }),
+        store,
+        stream::select(cleaners, cron_jobs).take_until(shutdown_signal()),
+    );
+    let mut controller = tokio::spawn(async move {
+        match one_shot {
+            Some(one_shot) => one_shot.run(results, deadline).await,
+            None => {
+                log_results(results).await;
+                Ok(())
+            }
+        }
+    });
+
+    // the runtime serves the admin server, flipping /ready once
+    // `initialized` is dropped, until SIGINT or SIGTERM
+    tokio::select! {
+        res = &mut controller => res??,
+        res = runtime.run() => {
+            if res.is_err() {
+                bail!("Aborted, reconciles in flight were dropped");
+            }
+            controller.await??;
+        }
+    }
+    tracing::info!("controller terminated");
+
+    Ok(())
+}
+
+/// Which of the PodCleaners `--exit` waits for were reconciled, and how.
+#[derive(Debug, Default)]
+struct OneShot {
+    /// By namespace and name
+    pending: HashSet<(Option<String>, String)>,
+    failed: Vec<String>,
+}
+
+impl OneShot {
+    fn new(keys: impl IntoIterator<Item = (Option<String>, String)>) -> Self {
+        Self {
+            pending: keys.into_iter().collect(),
+            failed: Vec::new(),
+        }
+    }
+
+    /// Records the outcome of a reconcile, only the first one of each
+    /// PodCleaner counts.
+    fn reconciled(&mut self, namespace: Option<String>, name: String, ok: bool) {
+        let key = (namespace, name);
+        if self.pending.remove(&key) && !ok {
+            self.failed
+                .push(format!("{}/{}", key.0.unwrap_or_default(), key.1));
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Follows the controller until every pending PodCleaner was reconciled.
+    async fn run<E, Q>(
+        mut self,
+        results: impl Stream<Item = Result<(ObjectRef<PodCleaner>, Action), controller::Error<E, Q>>>,
+        deadline: time::Instant,
+    ) -> Result<()>
+    where
+        E: std::fmt::Display,
+        controller::Error<E, Q>: std::fmt::Display,
+    {
+        futures::pin_mut!(results);
+        while !self.done() {
+            match time::timeout_at(deadline, results.next()).await {
+                Ok(Some(Ok((obj, _)))) => self.reconciled(obj.namespace, obj.name, true),
+                Ok(Some(Err(controller::Error::ReconcilerFailed(e, obj)))) => {
+                    tracing::error!("reconcile of {} failed: {}", obj, e);
+                    self.reconciled(obj.namespace, obj.name, false);
+                }
+                Ok(Some(Err(e))) => tracing::warn!("{}", e),
+                Ok(None) => bail!("Controller stopped before reconciling every PodCleaner"),
+                Err(_) => bail!(
+                    "Timed out with {} PodCleaners not reconciled yet",
+                    self.pending.len()
+                ),
+            }
+        }
+        if !self.failed.is_empty() {
+            bail!("Failed to reconcile {}", self.failed.join(", "));
+        }
+        tracing::info!("Reconciled every PodCleaner, exiting");
+        Ok(())
+    }
+}
+
+/// The list and watch parameters of both the PodCleaners and their CronJobs.
+fn watch_params(selector: Option<&str>) -> ListParams {
+    match selector {
+        Some(selector) => ListParams::default().labels(selector),
+        None => ListParams::default(),
+    }
+}
+
+struct Data {
+    client: Client,
+    /// Consecutive reconcile failures
+    failures: Mutex<Failures>,
+    /// `--skip-rbac`
+    skip_rbac: bool,
+    /// `--default-image`
+    default_image: String,
+    /// Whether the cluster supports CronJob time zones
+    supports_time_zone: bool,
+    /// Who the Events on PodCleaners are from
+    reporter: Reporter,
+    metrics: Arc<Metrics>,
+    /// `--requeue-interval`
+    requeue_interval: time::Duration,
+    /// `--error-backoff`
+    error_backoff: time::Duration,
+}
+
+/// Upper bound on the delay between reconnect attempts after watch errors.
+const WATCH_BACKOFF_MAX: time::Duration = time::Duration::from_secs(60);
+
+/// Logs the outcome of each reconcile until the controller stops. Watch
+/// errors are left to `backed_off`, which saw them first.
+async fn log_results<E, Q>(
+    results: impl Stream<Item = Result<(ObjectRef<PodCleaner>, Action), controller::Error<E, Q>>>,
+) where
+    E: std::error::Error + 'static,
+    Q: std::error::Error + 'static,
+{
+    results
         .for_each(|res| async move {
             match res {
                 Ok(o) => tracing::info!("reconciled {:?}", o),
+                Err(controller::Error::QueueError(_)) => {}
                 Err(e) => tracing::error!("reconcile failed: {:?}", e),
             }
         })
         .await;
-    tracing::info!("controller terminated");
+}
 
-    Ok(())
+/// Delays a watch after each error, doubling up to `max_delay` while they
+/// keep coming. The watcher re-lists right away on its own, which only
+/// hammers an apiserver that is already struggling.
+///
+/// The delay holds back this watch alone, reconciles carry on meanwhile.
+/// Errors and events are recorded in `metrics` as they pass, so only the
+/// watch itself delivering again ends its run of errors.
+fn backed_off<S, T, E>(
+    watch: &'static str,
+    stream: S,
+    metrics: Arc<Metrics>,
+    max_delay: time::Duration,
+) -> impl Stream<Item = Result<T, E>>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let failures = Arc::new(AtomicU32::new(0));
+    stream.then(move |res| {
+        let failures = failures.clone();
+        let metrics = metrics.clone();
+        async move {
+            match &res {
+                Ok(_) => {
+                    metrics.watch_recovered(watch);
+                    let failed = failures.swap(0, Ordering::Relaxed);
+                    if failed > 0 {
+                        tracing::info!(
+                            "{} watch recovered after {} consecutive failures",
+                            watch,
+                            failed
+                        );
+                    }
+                }
+                Err(e) => {
+                    metrics.watch_failed(watch, std::time::Instant::now());
+                    let failed = failures.fetch_add(1, Ordering::Relaxed) + 1;
+                    let delay = max_delay.min(time::Duration::from_secs(1 << failed.min(6)));
+                    tracing::warn!(
+                        "{} watch failed ({} consecutive), reconnecting in {:?}: {}",
+                        watch,
+                        failed,
+                        delay,
+                        e
+                    );
+                    time::sleep(delay).await;
+                }
+            }
+            res
+        }
+    })
 }
 
-struct Data {
-    client: Client,
+/// Resolves on SIGINT or SIGTERM, ending the watches so the reconciles in
+/// flight can finish.
+async fn shutdown_signal() {
+    let mut terminate = match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            tracing::warn!("Unable to listen for SIGTERM: {}", e);
+            let _ = signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+    tracing::info!("Shutting down, letting running reconciles finish");
 }
 
-fn error_policy(_error: &Error, _ctx: Context<Data>) -> Action {
-    Action::requeue(tokio::time::Duration::from_secs(1))
+/// Upper bound on the delay before retrying a failed reconcile.
+const RECONCILE_BACKOFF_MAX: time::Duration = time::Duration::from_secs(5 * 60);
+
+/// Upper bound on the delay before reconciling a PodCleaner that isn't
+/// healthy again.
+const RECONCILE_UNHEALTHY_REQUEUE: time::Duration = time::Duration::from_secs(60);
+
+/// `--requeue-interval` below this only loads the apiserver, children rarely
+/// drift and changes to them are watched anyway.
+const MIN_REQUEUE_INTERVAL: time::Duration = time::Duration::from_secs(10);
+
+/// Delay before retrying a reconcile that failed in a way retrying can't fix,
+/// editing the PodCleaner reconciles it right away anyway.
+const RECONCILE_PERMANENT_REQUEUE: time::Duration = time::Duration::from_secs(60 * 60);
+
+fn error_policy(error: &Error, ctx: Context<Data>) -> Action {
+    ctx.get_ref().metrics.failed();
+    if error.is_permanent() {
+        return Action::requeue(RECONCILE_PERMANENT_REQUEUE);
+    }
+    let attempts = match error {
+        Error::Retrying { attempts, .. } => *attempts,
+        _ => 1,
+    };
+    Action::requeue(jitter(
+        reconcile_backoff(ctx.get_ref().error_backoff, attempts),
+        &mut rand::thread_rng(),
+    ))
+}
+
+/// `base` after the first failure, doubling with every failure after that up
+/// to `RECONCILE_BACKOFF_MAX`, so a misconfigured cleaner doesn't spin hot.
+fn reconcile_backoff(base: time::Duration, attempts: u32) -> time::Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    RECONCILE_BACKOFF_MAX.min(base.saturating_mul(1 << exponent))
+}
+
+/// Spreads a delay over 80% to 120% of itself, still capped at
+/// `RECONCILE_BACKOFF_MAX`, so cleaners that broke together (say, after a
+/// webhook was installed) don't all retry in lockstep.
+fn jitter(delay: time::Duration, rng: &mut impl Rng) -> time::Duration {
+    RECONCILE_BACKOFF_MAX.min(delay.mul_f64(rng.gen_range(0.8..=1.2)))
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -308,6 +2245,30 @@ impl std::str::FromStr for Timeout {
     }
 }
 
+/// A `Timeout` of at least `MIN_REQUEUE_INTERVAL`.
+#[derive(Copy, Clone, Debug)]
+struct RequeueInterval(time::Duration);
+
+#[derive(Copy, Clone, Debug, thiserror::Error)]
+enum InvalidRequeueInterval {
+    #[error("invalid duration")]
+    Invalid,
+    #[error("must be at least 10s")]
+    TooShort,
+}
+
+impl std::str::FromStr for RequeueInterval {
+    type Err = InvalidRequeueInterval;
+
+    fn from_str(s: &str) -> Result<Self, InvalidRequeueInterval> {
+        let Timeout(interval) = s.parse().map_err(|_| InvalidRequeueInterval::Invalid)?;
+        if interval < MIN_REQUEUE_INTERVAL {
+            return Err(InvalidRequeueInterval::TooShort);
+        }
+        Ok(Self(interval))
+    }
+}
+
 async fn _init_timeout<F: Future>(deadline: Option<time::Instant>, future: F) -> Result<F::Output> {
     if let Some(deadline) = deadline {
         return time::timeout_at(deadline, future).await.map_err(Into::into);
@@ -315,3 +2276,1048 @@ async fn _init_timeout<F: Future>(deadline: Option<time::Instant>, future: F) ->
 
     Ok(future.await)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use clap::Parser;
+    use futures::{channel::mpsc, stream, StreamExt};
+    use k8s_openapi::{
+        api::{
+            batch::v1::{CronJob, Job},
+            core::v1::PodSpec,
+        },
+        apimachinery::pkg::version::Info,
+    };
+    use kube::{
+        core::ErrorResponse,
+        runtime::{controller, controller::Action, reflector::ObjectRef, watcher},
+        Client, CustomResourceExt, ResourceExt,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::Value;
+
+    use super::{
+        apply_cron_job, backed_off, condition, cron_job, cron_job_for, drain_cleaner, drifted_by,
+        finished, jitter, job_failure, log_results, reconcile_backoff, requeue_after, runs,
+        supports_time_zone, validate_filters, validate_schedule, validate_schedules,
+        validate_time_zone, watch_params, Args, Command, ConcurrencyPolicy, Error, Failures,
+        InvalidRequeueInterval, Metrics, OneShot, PodCleaner, PodCleanerSpec, PodCleanerStatus,
+        RequeueInterval, CLEANUP_DEADLINE_ANNOTATION, CLEANUP_JOB_POLL, RECONCILE_BACKOFF_MAX,
+        RECONCILE_UNHEALTHY_REQUEUE,
+    };
+
+    /// A PodCleaner named `nightly` with `spec`, with a uid so its children
+    /// get an owner reference.
+    fn cleaner(spec: Value) -> PodCleaner {
+        let spec: PodCleanerSpec = serde_json::from_value(spec).unwrap();
+        let mut generator = PodCleaner::new("nightly", spec);
+        generator.metadata.uid = Some("1234".to_string());
+        generator
+    }
+
+    /// The pod spec of the jobs `cj` starts.
+    fn cleaner_pod(cj: CronJob) -> PodSpec {
+        cj.spec
+            .unwrap()
+            .job_template
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap()
+    }
+
+    /// The arguments the cleaner is started with by `cj`.
+    fn cleaner_args(cj: &CronJob) -> Vec<String> {
+        cleaner_pod(cj.clone()).containers[0].args.clone().unwrap()
+    }
+
+    /// Follows a printer column JSONPath through the CRD schema, treating any
+    /// `[...]` filter as stepping into the array items.
+    fn resolves(schema: &Value, path: &str) -> bool {
+        let filters = regex::Regex::new(r"\[[^\]]*\]").unwrap();
+        let path = filters.replace_all(path, "[]");
+        let mut node = schema;
+        for segment in path.trim_start_matches('.').split('.') {
+            let (name, array) = match segment.strip_suffix("[]") {
+                Some(name) => (name, true),
+                None => (segment, false),
+            };
+            node = match node.pointer(&format!("/properties/{}", name)) {
+                Some(node) => node,
+                None => return false,
+            };
+            if array {
+                node = match node.get("items") {
+                    Some(items) => items,
+                    None => return false,
+                };
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn reconcile_backoff_doubles_up_to_the_cap() {
+        let second = Duration::from_secs(1);
+        let secs = |attempts| reconcile_backoff(second, attempts).as_secs();
+        assert_eq!(secs(1), 1);
+        assert_eq!(secs(2), 2);
+        assert_eq!(secs(5), 16);
+        assert_eq!(secs(9), 256);
+        assert_eq!(reconcile_backoff(second, 10), RECONCILE_BACKOFF_MAX);
+        assert_eq!(reconcile_backoff(second, u32::MAX), RECONCILE_BACKOFF_MAX);
+
+        let base = Duration::from_secs(10);
+        assert_eq!(reconcile_backoff(base, 1), base);
+        assert_eq!(reconcile_backoff(base, 3), Duration::from_secs(40));
+        assert_eq!(reconcile_backoff(base, 6), RECONCILE_BACKOFF_MAX);
+    }
+
+    #[test]
+    fn requeue_interval_is_at_least_10s() {
+        let parse = |s: &str| s.parse::<RequeueInterval>().map(|i| i.0);
+        assert_eq!(parse("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse("10s").unwrap(), Duration::from_secs(10));
+        assert!(matches!(parse("9s"), Err(InvalidRequeueInterval::TooShort)));
+        assert!(matches!(
+            parse("500ms"),
+            Err(InvalidRequeueInterval::TooShort)
+        ));
+        assert!(matches!(parse("0"), Err(InvalidRequeueInterval::TooShort)));
+        assert!(matches!(parse("5h"), Err(InvalidRequeueInterval::Invalid)));
+    }
+
+    #[test]
+    fn unhealthy_cleaners_are_requeued_sooner() {
+        let status = |ready: bool, last_run: Option<bool>| PodCleanerStatus {
+            conditions: [condition(None, "Ready", ready, "Applied", None)]
+                .into_iter()
+                .chain(last_run.map(|ok| condition(None, "LastRunSucceeded", ok, "Job", None)))
+                .collect(),
+            ..PodCleanerStatus::default()
+        };
+        let interval = Duration::from_secs(15 * 60);
+
+        assert_eq!(requeue_after(&status(true, None), interval), interval);
+        assert_eq!(requeue_after(&status(true, Some(true)), interval), interval);
+        assert_eq!(
+            requeue_after(&status(true, Some(false)), interval),
+            RECONCILE_UNHEALTHY_REQUEUE
+        );
+        assert_eq!(
+            requeue_after(&status(false, None), interval),
+            RECONCILE_UNHEALTHY_REQUEUE
+        );
+        // never later than asked for
+        let short = Duration::from_secs(20);
+        assert_eq!(requeue_after(&status(false, None), short), short);
+    }
+
+    #[test]
+    fn failures_count_up_until_a_success() {
+        let mut failures = Failures::default();
+        let nightly = ObjectRef::<PodCleaner>::new("nightly").within("apps");
+        let hourly = ObjectRef::<PodCleaner>::new("hourly").within("apps");
+
+        assert_eq!(failures.failed(nightly.clone()), 1);
+        assert_eq!(failures.failed(nightly.clone()), 2);
+        assert_eq!(failures.failed(hourly.clone()), 1);
+        assert_eq!(failures.failed(nightly.clone()), 3);
+
+        failures.succeeded(&nightly);
+        assert_eq!(failures.failed(nightly), 1);
+        assert_eq!(failures.failed(hourly), 2);
+    }
+
+    #[test]
+    fn jitter_stays_near_the_backoff() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for attempts in 1..12 {
+            let backoff = reconcile_backoff(Duration::from_secs(1), attempts);
+            for _ in 0..50 {
+                let delay = jitter(backoff, &mut rng);
+                assert!(
+                    delay >= backoff.mul_f64(0.8),
+                    "{:?} for {:?}",
+                    delay,
+                    backoff
+                );
+                assert!(
+                    delay <= backoff.mul_f64(1.2),
+                    "{:?} for {:?}",
+                    delay,
+                    backoff
+                );
+                assert!(delay <= RECONCILE_BACKOFF_MAX);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn reconciles_dont_end_a_run_of_watch_errors() {
+        let metrics = Arc::new(Metrics::default());
+        let (tx, rx) = mpsc::unbounded::<Result<(), String>>();
+        let watch = backed_off("PodCleaner", rx, metrics.clone(), Duration::ZERO);
+        futures::pin_mut!(watch);
+        let failing = || metrics.watch_failing_for(Duration::ZERO, Instant::now());
+
+        for _ in 0..3 {
+            tx.unbounded_send(Err("connection refused".to_string()))
+                .unwrap();
+            assert!(watch.next().await.unwrap().is_err());
+
+            // requeued PodCleaners keep reconciling while the watch is down
+            metrics.reconciled(Duration::from_millis(10));
+            let reconciled: Result<_, controller::Error<Error, watcher::Error>> =
+                Ok((ObjectRef::new("cleaner"), Action::await_change()));
+            log_results(stream::iter(vec![reconciled])).await;
+            assert!(failing().is_some());
+        }
+
+        tx.unbounded_send(Ok(())).unwrap();
+        assert!(watch.next().await.unwrap().is_ok());
+        assert_eq!(failing(), None);
+    }
+
+    #[test]
+    fn rejected_children_are_permanent_failures() {
+        let rejected = |code| {
+            Error::CronJobCreationFailed(kube::Error::Api(ErrorResponse {
+                status: "Failure".to_string(),
+                message: "rejected".to_string(),
+                reason: "Invalid".to_string(),
+                code,
+            }))
+        };
+        assert!(rejected(422).is_permanent());
+        assert!(Error::Retrying {
+            attempts: 3,
+            source: Box::new(rejected(400)),
+        }
+        .is_permanent());
+        assert!(!rejected(500).is_permanent());
+        assert!(!rejected(409).is_permanent());
+        assert!(!Error::MissingObjectKey(".metadata.namespace").is_permanent());
+    }
+
+    #[test]
+    fn finished_means_complete_or_failed() {
+        let job = |conditions: serde_json::Value| -> Job {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": "cleaner-clean-job-27000000" },
+                "status": { "conditions": conditions },
+            }))
+            .unwrap()
+        };
+        assert!(finished(&job(serde_json::json!([
+            { "type": "Complete", "status": "True" },
+        ]))));
+        assert!(finished(&job(serde_json::json!([
+            { "type": "Failed", "status": "True" },
+        ]))));
+        assert!(!finished(&job(serde_json::json!([
+            { "type": "Suspended", "status": "True" },
+        ]))));
+        assert!(!finished(&job(serde_json::json!([]))));
+    }
+
+    #[test]
+    fn selector_shards_both_watches() {
+        assert_eq!(watch_params(None).label_selector, None);
+        assert_eq!(
+            watch_params(Some("team=data-eng"))
+                .label_selector
+                .as_deref(),
+            Some("team=data-eng")
+        );
+
+        let mut generator = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+        }));
+        generator.metadata.labels = Some(
+            [("team".to_string(), "data-eng".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let cj = cron_job(&generator, "apps", "shopvac:test");
+        assert_eq!(
+            cj.labels().get("team").map(String::as_str),
+            Some("data-eng")
+        );
+        assert_eq!(
+            cj.labels().get("shopvac.io/owned-by").map(String::as_str),
+            Some("nightly")
+        );
+    }
+
+    #[test]
+    fn drift_is_what_others_own_of_the_desired_spec() {
+        let entry = |manager: &str, subresource: Option<&str>, spec: Value| {
+            serde_json::json!({
+                "manager": manager,
+                "operation": "Update",
+                "subresource": subresource,
+                "fieldsType": "FieldsV1",
+                "fieldsV1": { "f:spec": spec },
+            })
+        };
+        let cj: CronJob = serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": "nightly-clean-job",
+                "managedFields": [
+                    entry("podcleaner.kube-rt.shopvac.io", None, serde_json::json!({ "f:schedule": {} })),
+                    entry("kube-controller-manager", Some("status"), serde_json::json!({ "f:jobTemplate": {} })),
+                    entry("kubectl-edit", None, serde_json::json!({ "f:jobTemplate": { "f:spec": {} } })),
+                    entry("kubectl-patch", None, serde_json::json!({ "f:schedule": {} })),
+                    entry("kubectl-annotate", None, Value::Null),
+                    entry("helm", None, serde_json::json!({ "f:startingDeadlineSeconds": {} })),
+                ],
+            },
+        }))
+        .unwrap();
+        let desired = serde_json::json!({ "spec": { "schedule": "@daily", "jobTemplate": {} } });
+
+        assert_eq!(
+            drifted_by(&cj, &desired),
+            vec!["kubectl-edit", "kubectl-patch"]
+        );
+    }
+
+    #[tokio::test]
+    async fn edited_cron_job_is_restored() {
+        let mut generator = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+        }));
+        generator.metadata.namespace = Some("apps".to_string());
+        generator.metadata.uid = Some("1234".to_string());
+        let desired = serde_json::to_value(cron_job(&generator, "apps", "shopvac:test")).unwrap();
+        let image = "/spec/jobTemplate/spec/template/spec/containers/0/image";
+        let args = "/spec/jobTemplate/spec/template/spec/containers/0/args";
+
+        let mut edited = desired.clone();
+        *edited.pointer_mut(image).unwrap() = serde_json::json!("shopvac:edited");
+        *edited.pointer_mut(args).unwrap() = serde_json::json!(["clean"]);
+        edited["metadata"]["managedFields"] = serde_json::json!([{
+            "manager": "kubectl-edit",
+            "operation": "Update",
+            "fieldsType": "FieldsV1",
+            "fieldsV1": { "f:spec": { "f:jobTemplate": { "f:spec": {} } } },
+        }]);
+
+        let (service, mut handle) =
+            tower_test::mock::pair::<http::Request<hyper::Body>, http::Response<hyper::Body>>();
+        let client = Client::new(service, "default");
+        let respond = |body: &Value| {
+            http::Response::builder()
+                .body(hyper::Body::from(body.to_string()))
+                .unwrap()
+        };
+        let path = "/apis/batch/v1/namespaces/apps/cronjobs/nightly-clean-job";
+        let server = tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.expect("get not sent");
+            assert_eq!(
+                (request.method().as_str(), request.uri().path()),
+                ("GET", path)
+            );
+            send.send_response(respond(&edited));
+
+            let (request, send) = handle.next_request().await.expect("repair not sent");
+            assert_eq!(request.uri().path(), path);
+            assert_eq!(
+                request.headers()["content-type"],
+                "application/strategic-merge-patch+json"
+            );
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            let patch: Value = serde_json::from_slice(&body).unwrap();
+            let template = &patch["spec"]["jobTemplate"];
+            assert_eq!(template["$patch"], "replace");
+            assert_eq!(
+                patch.pointer(image).unwrap(),
+                desired.pointer(image).unwrap()
+            );
+            assert_eq!(patch.pointer(args).unwrap(), desired.pointer(args).unwrap());
+            send.send_response(respond(&desired));
+
+            let (request, send) = handle.next_request().await.expect("apply not sent");
+            let query = request.uri().query().unwrap().to_string();
+            assert!(query.contains("force=true"), "{}", query);
+            assert!(
+                query.contains("fieldManager=podcleaner.kube-rt.shopvac.io"),
+                "{}",
+                query
+            );
+            send.send_response(respond(&desired));
+
+            let (request, send) = handle.next_request().await.expect("list not sent");
+            assert_eq!(
+                request.uri().path(),
+                "/apis/batch/v1/namespaces/apps/cronjobs"
+            );
+            assert!(request
+                .uri()
+                .query()
+                .unwrap()
+                .contains("labelSelector=shopvac.io%2Fowned-by%3Dnightly"));
+            send.send_response(respond(&serde_json::json!({
+                "apiVersion": "batch/v1",
+                "kind": "CronJobList",
+                "metadata": {},
+                "items": [],
+            })));
+        });
+
+        let (cj, repaired) = apply_cron_job(&generator, client, "apps", "shopvac:test", None)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert_eq!(cj.name(), "nightly-clean-job");
+        assert_eq!(
+            repaired,
+            vec!["nightly-clean-job (changed by kubectl-edit)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_cleaner_takes_its_rbac_along() {
+        let mut generator = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+        }));
+        generator.metadata.namespace = Some("apps".to_string());
+
+        let (service, mut handle) =
+            tower_test::mock::pair::<http::Request<hyper::Body>, http::Response<hyper::Body>>();
+        let client = Client::new(service, "default");
+        let server =
+            tokio::spawn(async move {
+                let mut deleted = Vec::new();
+                while let Some((request, send)) = handle.next_request().await {
+                    let path = request.uri().path().to_string();
+                    let body =
+                        if request.method() == http::Method::DELETE {
+                            deleted.push(path);
+                            serde_json::json!({
+                                "apiVersion": "v1",
+                                "kind": "Status",
+                                "status": "Success",
+                                "message": "",
+                                "reason": "",
+                                "code": 200,
+                            })
+                        } else {
+                            assert!(request
+                                .uri()
+                                .query()
+                                .unwrap()
+                                .contains("labelSelector=shopvac.io%2Fowned-by%3Dnightly"));
+                            let (api_version, kind, name) = match path.as_str() {
+                        "/api/v1/namespaces/apps/serviceaccounts" => {
+                            ("v1", "ServiceAccount", "nightly-shopvac-sa")
+                        }
+                        "/apis/rbac.authorization.k8s.io/v1/namespaces/apps/roles" => {
+                            ("rbac.authorization.k8s.io/v1", "Role", "nightly-shopvac-role")
+                        }
+                        "/apis/rbac.authorization.k8s.io/v1/namespaces/apps/rolebindings" => {
+                            ("rbac.authorization.k8s.io/v1", "RoleBinding", "nightly-shopvac-rb")
+                        }
+                        path => panic!("unexpected request to {}", path),
+                    };
+                            serde_json::json!({
+                                "apiVersion": api_version,
+                                "kind": format!("{}List", kind),
+                                "metadata": {},
+                                "items": [{
+                                    "metadata": { "name": name, "namespace": "apps" },
+                                    "roleRef": { "apiGroup": "", "kind": "Role", "name": "" },
+                                }],
+                            })
+                        };
+                    send.send_response(
+                        http::Response::builder()
+                            .body(hyper::Body::from(body.to_string()))
+                            .unwrap(),
+                    );
+                }
+                deleted
+            });
+
+        prune_rbac(&generator, client, "apps", &[]).await;
+        let deleted = server.await.unwrap();
+
+        assert_eq!(
+            deleted,
+            vec![
+                "/api/v1/namespaces/apps/serviceaccounts/nightly-shopvac-sa",
+                "/apis/rbac.authorization.k8s.io/v1/namespaces/apps/roles/nightly-shopvac-role",
+                "/apis/rbac.authorization.k8s.io/v1/namespaces/apps/rolebindings/nightly-shopvac-rb",
+            ]
+        );
+    }
+
+    /// Drains `generator` against an apiserver with one cleanup still
+    /// running, returning the outcome and the patches sent to the PodCleaner.
+    async fn drain_with_a_running_cleanup(generator: &PodCleaner) -> (Option<Action>, Vec<Value>) {
+        let (service, mut handle) =
+            tower_test::mock::pair::<http::Request<hyper::Body>, http::Response<hyper::Body>>();
+        let client = Client::new(service, "default");
+        let server = tokio::spawn(async move {
+            let mut patched = Vec::new();
+            while let Some((request, send)) = handle.next_request().await {
+                let path = request.uri().path().to_string();
+                let body = match (request.method().as_str(), path.as_str()) {
+                    ("PATCH", "/apis/batch/v1/namespaces/apps/cronjobs/nightly-clean-job") => {
+                        serde_json::json!({
+                            "apiVersion": "batch/v1",
+                            "kind": "CronJob",
+                            "metadata": { "name": "nightly-clean-job", "namespace": "apps" },
+                        })
+                    }
+                    ("GET", "/apis/batch/v1/namespaces/apps/jobs") => serde_json::json!({
+                        "apiVersion": "batch/v1",
+                        "kind": "JobList",
+                        "metadata": {},
+                        "items": [{
+                            "metadata": {
+                                "name": "nightly-clean-job-27800000",
+                                "namespace": "apps",
+                                "ownerReferences": [{
+                                    "apiVersion": "batch/v1",
+                                    "kind": "CronJob",
+                                    "name": "nightly-clean-job",
+                                    "uid": "1234",
+                                }],
+                            },
+                            "status": { "active": 1 },
+                        }],
+                    }),
+                    ("PATCH", "/apis/shopvac.io/v1/namespaces/apps/podcleaners/nightly") => {
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        patched.push(serde_json::from_slice(&body).unwrap());
+                        serde_json::json!({
+                            "apiVersion": "shopvac.io/v1",
+                            "kind": "PodCleaner",
+                            "metadata": { "name": "nightly", "namespace": "apps" },
+                            "spec": { "schedule": "@daily", "delete_older_than": 7 },
+                        })
+                    }
+                    (method, path) => panic!("unexpected {} {}", method, path),
+                };
+                send.send_response(
+                    http::Response::builder()
+                        .body(hyper::Body::from(body.to_string()))
+                        .unwrap(),
+                );
+            }
+            patched
+        });
+
+        let drained = drain_cleaner(generator, client).await.unwrap();
+        (drained, server.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn deleted_cleaner_waits_for_running_cleanups_until_its_deadline() {
+        let mut generator = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+        }));
+        generator.metadata.namespace = Some("apps".to_string());
+
+        // the first look records the deadline and comes back later
+        let (drained, patched) = drain_with_a_running_cleanup(&generator).await;
+        assert_eq!(drained, Some(Action::requeue(CLEANUP_JOB_POLL)));
+        assert_eq!(patched.len(), 1);
+        let deadline = patched[0]["metadata"]["annotations"][CLEANUP_DEADLINE_ANNOTATION]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let left = chrono::DateTime::parse_from_rfc3339(&deadline).unwrap() - chrono::Utc::now();
+        assert!(left > chrono::Duration::minutes(4), "{}", left);
+
+        // later looks keep the recorded deadline
+        generator
+            .annotations_mut()
+            .insert(CLEANUP_DEADLINE_ANNOTATION.to_string(), deadline);
+        let (drained, patched) = drain_with_a_running_cleanup(&generator).await;
+        assert_eq!(drained, Some(Action::requeue(CLEANUP_JOB_POLL)));
+        assert!(patched.is_empty());
+
+        // and stop waiting once it passed
+        let passed = (chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339();
+        generator
+            .annotations_mut()
+            .insert(CLEANUP_DEADLINE_ANNOTATION.to_string(), passed);
+        let (drained, patched) = drain_with_a_running_cleanup(&generator).await;
+        assert_eq!(drained, None);
+        assert!(patched.is_empty());
+    }
+
+    #[test]
+    fn one_shot_waits_for_the_first_outcome_of_each() {
+        let key = |name: &str| (Some("apps".to_string()), name.to_string());
+        let mut one_shot = OneShot::new([key("a"), key("b")]);
+        assert!(!one_shot.done());
+
+        one_shot.reconciled(Some("apps".to_string()), "b".to_string(), false);
+        // a retry that succeeds doesn't undo the failure
+        one_shot.reconciled(Some("apps".to_string()), "b".to_string(), true);
+        // nor do PodCleaners created after startup count
+        one_shot.reconciled(Some("apps".to_string()), "new".to_string(), false);
+        assert!(!one_shot.done());
+
+        one_shot.reconciled(Some("apps".to_string()), "a".to_string(), true);
+        assert!(one_shot.done());
+        assert_eq!(one_shot.failed, vec!["apps/b"]);
+
+        assert!(OneShot::new([]).done());
+    }
+
+    #[test]
+    fn validates_time_zones() {
+        assert!(validate_time_zone("Europe/Berlin").is_ok());
+        assert!(validate_time_zone("UTC").is_ok());
+        assert!(validate_time_zone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn time_zones_need_1_25() {
+        let version = |major: &str, minor: &str| Info {
+            major: major.to_string(),
+            minor: minor.to_string(),
+            ..Info::default()
+        };
+        assert!(!supports_time_zone(&version("1", "22")));
+        assert!(!supports_time_zone(&version("1", "24+")));
+        assert!(supports_time_zone(&version("1", "25")));
+        assert!(supports_time_zone(&version("1", "27+")));
+        assert!(!supports_time_zone(&version("", "")));
+    }
+
+    #[test]
+    fn job_failures_carry_the_reason() {
+        let job = |conditions: Value| -> Job {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": "nightly-clean-job-1" },
+                "status": { "conditions": conditions },
+            }))
+            .unwrap()
+        };
+
+        let succeeded = job(serde_json::json!([{ "type": "Complete", "status": "True" }]));
+        assert!(job_failure(&succeeded).is_none());
+
+        let failed = job(serde_json::json!([{
+            "type": "Failed",
+            "status": "True",
+            "reason": "BackoffLimitExceeded",
+            "message": "Job has reached the specified backoff limit",
+            "lastTransitionTime": "2022-05-01T03:00:00Z",
+        }]));
+        let (at, message) = job_failure(&failed).unwrap();
+        assert_eq!(at.as_deref(), Some("2022-05-01T03:00:00+00:00"));
+        assert_eq!(
+            message,
+            "BackoffLimitExceeded: Job has reached the specified backoff limit"
+        );
+    }
+
+    #[test]
+    fn validates_schedules() {
+        assert!(validate_schedule("*/5 * * * *").is_ok());
+        assert!(validate_schedule("0 3 * * 1-5").is_ok());
+        assert!(validate_schedule("@hourly").is_ok());
+        assert!(validate_schedule("every 5 minutes").is_err());
+        assert!(validate_schedule("61 * * * *").is_err());
+        assert!(validate_schedule("").is_err());
+    }
+
+    #[test]
+    fn cron_job_carries_resources_and_security_contexts() {
+        let mut generator = cleaner(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+            "resources": { "limits": { "memory": "512Mi" } },
+        }));
+        generator.metadata.namespace = Some("apps".to_string());
+        generator.metadata.uid = Some("1234".to_string());
+
+        let produced = serde_json::to_value(cron_job(&generator, "apps", "shopvac:test")).unwrap();
+        let pod = cleaner_pod(serde_json::from_value(produced).unwrap());
+        let container = &pod.containers[0];
+
+        let limits = container
+            .resources
+            .as_ref()
+            .unwrap()
+            .limits
+            .as_ref()
+            .unwrap();
+        assert_eq!(limits["memory"].0, "512Mi");
+        let security = container.security_context.as_ref().unwrap();
+        assert_eq!(security.read_only_root_filesystem, Some(true));
+        assert_eq!(security.allow_privilege_escalation, Some(false));
+        assert_eq!(
+            security.capabilities.as_ref().unwrap().drop,
+            Some(vec!["ALL".to_string()])
+        );
+        let pod_security = pod.security_context.as_ref().unwrap();
+        assert_eq!(pod_security.run_as_non_root, Some(true));
+        assert_eq!(
+            pod_security.seccomp_profile.as_ref().unwrap().type_,
+            "RuntimeDefault"
+        );
+    }
+
+    #[test]
+    fn cron_job_limits_default_and_can_be_overridden() {
+        let produce = |spec: Value| {
+            cron_job(&cleaner(spec), "apps", "shopvac:test")
+                .spec
+                .unwrap()
+        };
+
+        let defaults = produce(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+        }));
+        assert_eq!(defaults.successful_jobs_history_limit, Some(1));
+        assert_eq!(defaults.failed_jobs_history_limit, Some(1));
+        let job = defaults.job_template.spec.unwrap();
+        assert_eq!(job.backoff_limit, Some(2));
+        assert_eq!(job.active_deadline_seconds, Some(900));
+        assert_eq!(job.ttl_seconds_after_finished, Some(86400));
+
+        let overridden = produce(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+            "successful_jobs_history_limit": 3,
+            "backoff_limit": 0,
+            "active_deadline_seconds": 3600,
+        }));
+        assert_eq!(overridden.successful_jobs_history_limit, Some(3));
+        let job = overridden.job_template.spec.unwrap();
+        assert_eq!(job.backoff_limit, Some(0));
+        assert_eq!(job.active_deadline_seconds, Some(3600));
+    }
+
+    #[test]
+    fn cron_job_carries_scheduling_constraints() {
+        let generator = cleaner(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+            "node_selector": { "pool": "batch" },
+            "tolerations": [{
+                "key": "dedicated",
+                "operator": "Equal",
+                "value": "batch",
+                "effect": "NoSchedule",
+            }],
+            "affinity": {
+                "nodeAffinity": {
+                    "requiredDuringSchedulingIgnoredDuringExecution": {
+                        "nodeSelectorTerms": [{
+                            "matchExpressions": [{
+                                "key": "kubernetes.io/arch",
+                                "operator": "In",
+                                "values": ["amd64"],
+                            }],
+                        }],
+                    },
+                },
+            },
+            "priority_class_name": "low",
+        }));
+
+        let produced = serde_json::to_value(cron_job(&generator, "apps", "shopvac:test")).unwrap();
+        let pod = cleaner_pod(serde_json::from_value(produced).unwrap());
+
+        assert_eq!(pod.node_selector.unwrap()["pool"], "batch");
+        let tolerations = pod.tolerations.unwrap();
+        assert_eq!(tolerations[0].key.as_deref(), Some("dedicated"));
+        assert_eq!(tolerations[0].effect.as_deref(), Some("NoSchedule"));
+        assert!(pod.affinity.unwrap().node_affinity.is_some());
+        assert_eq!(pod.priority_class_name.as_deref(), Some("low"));
+    }
+
+    #[test]
+    fn metadata_is_propagated_to_the_jobs() {
+        let mut generator = cleaner(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+            "propagate_labels": ["cost-center", "owner"],
+            "propagate_annotations": ["owner-email"],
+            "job_labels": { "owner": "platform" },
+            "pod_annotations": { "sidecar.istio.io/inject": "false" },
+        }));
+        generator.metadata.labels = Some(
+            [
+                ("cost-center".to_string(), "42".to_string()),
+                ("owner".to_string(), "data-eng".to_string()),
+                ("tier".to_string(), "batch".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        generator.metadata.annotations = Some(
+            [
+                ("owner-email".to_string(), "data@example.com".to_string()),
+                ("note".to_string(), "not copied".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let cj = cron_job(&generator, "apps", "shopvac:test");
+        assert_eq!(cj.labels()["app.kubernetes.io/managed-by"], "shopvac");
+        assert_eq!(cj.labels()["tier"], "batch");
+        assert_eq!(
+            cj.annotations().keys().collect::<Vec<_>>(),
+            vec!["owner-email"]
+        );
+
+        let job = cj.spec.unwrap().job_template;
+        let job_labels = job.metadata.unwrap().labels.unwrap();
+        assert_eq!(job_labels["cost-center"], "42");
+        assert_eq!(job_labels["owner"], "platform");
+        assert_eq!(job_labels["app.kubernetes.io/managed-by"], "shopvac");
+        assert!(!job_labels.contains_key("tier"));
+
+        let pod = job.spec.unwrap().template.metadata.unwrap();
+        assert_eq!(pod.labels.unwrap(), job_labels);
+        let pod_annotations = pod.annotations.unwrap();
+        assert_eq!(pod_annotations["owner-email"], "data@example.com");
+        assert_eq!(pod_annotations["sidecar.istio.io/inject"], "false");
+        assert!(!pod_annotations.contains_key("note"));
+    }
+
+    #[test]
+    fn service_account_is_named_after_the_cleaner() {
+        let service_account = |spec: Value| {
+            cleaner_pod(cron_job(&cleaner(spec), "apps", "shopvac:test")).service_account_name
+        };
+
+        assert_eq!(
+            service_account(serde_json::json!({
+                "schedule": "@hourly",
+                "delete_older_than": 7,
+            }))
+            .as_deref(),
+            Some("nightly-shopvac-sa")
+        );
+        assert_eq!(
+            service_account(serde_json::json!({
+                "schedule": "@hourly",
+                "delete_older_than": 7,
+                "service_account_name": "cleaner",
+            }))
+            .as_deref(),
+            Some("cleaner")
+        );
+    }
+
+    #[test]
+    fn concurrency_policy_defaults_to_forbid() {
+        let spec: PodCleanerSpec = serde_json::from_value(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+        }))
+        .unwrap();
+        assert_eq!(spec.concurrency_policy, ConcurrencyPolicy::Forbid);
+
+        let typo = serde_json::from_value::<PodCleanerSpec>(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+            "concurrency_policy": "forbid",
+        }));
+        assert!(typo.is_err());
+    }
+
+    #[test]
+    fn dry_run_leaves_out_actually_delete() {
+        let args = |dry_run: bool| {
+            let generator = cleaner(serde_json::json!({
+                "schedule": "@hourly",
+                "delete_older_than": 7,
+                "dry_run": dry_run,
+            }));
+            cleaner_args(&cron_job(&generator, "apps", "shopvac:test"))
+        };
+
+        assert!(args(false).contains(&"--actually-delete".to_string()));
+        let dry = args(true);
+        assert!(!dry.contains(&"--actually-delete".to_string()));
+        assert!(dry.contains(&"--report-configmap".to_string()));
+    }
+
+    #[test]
+    fn filters_become_cleaner_flags() {
+        let generator = cleaner(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+            "pod_phases": ["Succeeded", "Failed"],
+            "exclude_pod_pattern": "^keep-",
+            "protection_annotation": "shopvac.io/protect",
+            "owner_kinds": ["Job", "Workflow"],
+            "max_delete": 500,
+        }));
+        let args = cleaner_args(&cron_job(&generator, "apps", "shopvac:test")).join(" ");
+
+        assert!(args.contains("--phase Succeeded,Failed"));
+        assert!(args.contains("--exclude-pod-pattern ^keep-"));
+        assert!(args.contains("--protection-annotation shopvac.io/protect"));
+        assert!(args.contains("--owner-kind Job,Workflow"));
+        assert!(args.contains("--max-pods 500"));
+        assert!(!args.contains("--naked-only"));
+    }
+
+    #[test]
+    fn naked_only_conflicts_with_owner_kinds() {
+        let naked = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+            "naked_only": true,
+        }));
+        assert!(validate_filters(&naked.spec).is_ok());
+        let args = cleaner_args(&cron_job(&naked, "apps", "shopvac:test"));
+        assert!(args.contains(&"--naked-only".to_string()));
+
+        let both = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+            "naked_only": true,
+            "owner_kinds": ["Job"],
+        }));
+        assert_eq!(
+            validate_filters(&both.spec).unwrap_err().0,
+            "ConflictingOwners"
+        );
+    }
+
+    #[test]
+    fn extra_schedules_get_their_own_cron_jobs() {
+        let generator = cleaner(serde_json::json!({
+            "schedule": "0 12 * * *",
+            "delete_older_than": 7,
+            "schedules": [
+                { "name": "night", "schedule": "0 2 * * *", "delete_older_than": 1 },
+            ],
+        }));
+        assert!(validate_schedules(&generator.spec).is_ok());
+
+        let cron_jobs: Vec<CronJob> = runs(&generator)
+            .iter()
+            .map(|run| cron_job_for(&generator, "apps", "shopvac:test", run))
+            .collect();
+        let names: Vec<String> = cron_jobs.iter().map(|cj| cj.name()).collect();
+        assert_eq!(names, vec!["nightly-clean-job", "nightly-clean-job-night"]);
+
+        assert_eq!(cron_jobs[1].spec.as_ref().unwrap().schedule, "0 2 * * *");
+        let args = cleaner_args(&cron_jobs[1]).join(" ");
+        assert!(args.contains("--older-than 1"));
+        assert!(args.contains("--report-configmap nightly-night-report"));
+    }
+
+    #[test]
+    fn extra_schedule_names_must_be_unique() {
+        let spec: PodCleanerSpec = serde_json::from_value(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 7,
+            "schedules": [
+                { "name": "night", "schedule": "0 2 * * *", "delete_older_than": 1 },
+                { "name": "night", "schedule": "0 3 * * *", "delete_older_than": 2 },
+            ],
+        }))
+        .unwrap();
+        assert!(validate_schedules(&spec).unwrap_err().contains("\"night\""));
+    }
+
+    #[test]
+    fn crd_subcommand_prints_unless_installing() {
+        let args = Args::try_parse_from(["shopvac-controller", "crd"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Crd { install: false })
+        ));
+
+        let args = Args::try_parse_from(["shopvac-controller", "crd", "--install"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Crd { install: true })));
+
+        let args = Args::try_parse_from(["shopvac-controller", "--ensure-crds"]).unwrap();
+        assert!(args.command.is_none());
+        assert!(args.ensure_crds);
+
+        let crd: Value =
+            serde_yaml::from_str(&serde_yaml::to_string(&PodCleaner::crd()).unwrap()).unwrap();
+        assert_eq!(crd["metadata"]["name"], "podcleaners.shopvac.io");
+    }
+
+    #[test]
+    fn suspend_defaults_to_false() {
+        let spec: PodCleanerSpec = serde_json::from_value(serde_json::json!({
+            "schedule": "@hourly",
+            "delete_older_than": 7,
+        }))
+        .unwrap();
+        assert!(!spec.suspend);
+    }
+
+    #[test]
+    fn schema_constrains_schedule_and_age() {
+        let crd = serde_json::to_value(PodCleaner::crd()).unwrap();
+        let spec = &crd["spec"]["versions"][0]["schema"]["openAPIV3Schema"]["properties"]["spec"]
+            ["properties"];
+
+        let pattern = regex::Regex::new(spec["schedule"]["pattern"].as_str().unwrap()).unwrap();
+        assert!(pattern.is_match("*/5 * * * *"));
+        assert!(pattern.is_match("@hourly"));
+        assert!(!pattern.is_match("every 5 minutes"));
+        assert!(!pattern.is_match("@fortnightly"));
+
+        // the cleaner takes any number of days, not just what fits an i8
+        assert_eq!(spec["delete_older_than"]["minimum"].as_f64(), Some(1.0));
+        assert_eq!(spec["delete_older_than"]["maximum"], Value::Null);
+        assert_eq!(spec["delete_older_than"]["format"], "uint32");
+        let extra = &spec["schedules"]["items"]["properties"]["delete_older_than"];
+        assert_eq!(extra["minimum"].as_f64(), Some(1.0));
+        assert_eq!(extra["maximum"], Value::Null);
+    }
+
+    #[test]
+    fn long_retention_is_passed_on_in_days() {
+        let generator = cleaner(serde_json::json!({
+            "schedule": "@daily",
+            "delete_older_than": 200,
+        }));
+
+        let args = cleaner_args(&cron_job(&generator, "apps", "shopvac:test"));
+        let at = args.iter().position(|a| a == "--older-than").unwrap();
+        assert_eq!(args[at + 1], "200");
+    }
+
+    #[test]
+    fn printer_columns_match_the_schema() {
+        let crd = serde_json::to_value(PodCleaner::crd()).unwrap();
+        let version = &crd["spec"]["versions"][0];
+        let schema = &version["schema"]["openAPIV3Schema"];
+        let columns = version["additionalPrinterColumns"].as_array().unwrap();
+
+        assert_eq!(columns.len(), 6);
+        for column in columns {
+            let path = column["jsonPath"].as_str().unwrap();
+            assert!(resolves(schema, path), "{} not in the schema", path);
+        }
+        assert_eq!(
+            crd["spec"]["names"]["categories"],
+            serde_json::json!(["all"])
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Prometheus metrics of the reconciler and the health of its watch, served
+//! on `--metrics-addr`.
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Write as _,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use kube::runtime::reflector::Store;
+
+use crate::PodCleaner;
+
+/// Longest gap between two watch errors that still counts as failing
+/// continuously, a bit over the longest reconnect backoff.
+const WATCH_ERROR_GAP: Duration = Duration::from_secs(2 * 60);
+
+/// Upper bounds of the reconcile duration histogram buckets, in seconds.
+const DURATION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default)]
+struct Counts {
+    reconciled: u64,
+    failed: u64,
+    /// Per bucket of `DURATION_BUCKETS`, not cumulative
+    duration_buckets: Vec<u64>,
+    duration_sum: f64,
+    child_apply_errors: BTreeMap<&'static str, u64>,
+    watch_errors: u64,
+    /// First and latest error of the current run of errors, by watch
+    watch_failing: BTreeMap<&'static str, (Instant, Instant)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counts: Mutex<Counts>,
+}
+
+impl Metrics {
+    /// Records a reconcile that ran to completion, successful or not.
+    pub fn reconciled(&self, took: Duration) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.reconciled += 1;
+        counts.duration_sum += took.as_secs_f64();
+        if counts.duration_buckets.is_empty() {
+            counts.duration_buckets = vec![0; DURATION_BUCKETS.len()];
+        }
+        if let Some(bucket) = DURATION_BUCKETS
+            .iter()
+            .position(|le| took.as_secs_f64() <= *le)
+        {
+            counts.duration_buckets[bucket] += 1;
+        }
+    }
+
+    /// Records a reconcile that ended in an error, from `error_policy`.
+    pub fn failed(&self) {
+        self.counts.lock().unwrap().failed += 1;
+    }
+
+    /// Records a child object of `kind` that couldn't be applied.
+    pub fn child_apply_failed(&self, kind: &'static str) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .child_apply_errors
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    /// Records a failed watch of PodCleaners or their CronJobs, `watch`
+    /// names which.
+    pub fn watch_failed(&self, watch: &'static str, at: Instant) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.watch_errors += 1;
+        let run = match counts.watch_failing.get(watch) {
+            Some(&(first, last)) if at.saturating_duration_since(last) <= WATCH_ERROR_GAP => {
+                (first, at)
+            }
+            _ => (at, at),
+        };
+        counts.watch_failing.insert(watch, run);
+    }
+
+    /// Records the watch delivering an event again, ending its run of
+    /// errors. Reconciles say nothing about the watch, requeues keep them
+    /// going while it fails.
+    pub fn watch_recovered(&self, watch: &'static str) {
+        self.counts.lock().unwrap().watch_failing.remove(watch);
+    }
+
+    /// How long a watch has been failing without a break, the longest of
+    /// them when that is `unhealthy_after` or longer.
+    pub fn watch_failing_for(&self, unhealthy_after: Duration, now: Instant) -> Option<Duration> {
+        self.counts
+            .lock()
+            .unwrap()
+            .watch_failing
+            .values()
+            .filter(|(_, last)| now.saturating_duration_since(*last) <= WATCH_ERROR_GAP)
+            .map(|(first, _)| now.saturating_duration_since(*first))
+            .filter(|failing| *failing >= unhealthy_after)
+            .max()
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self, podcleaners: usize) -> String {
+        let counts = self.counts.lock().unwrap();
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP shopvac_reconcile_total Reconciles of PodCleaners by result."
+        );
+        let _ = writeln!(out, "# TYPE shopvac_reconcile_total counter");
+        let _ = writeln!(
+            out,
+            "shopvac_reconcile_total{{result=\"ok\"}} {}",
+            counts.reconciled.saturating_sub(counts.failed)
+        );
+        let _ = writeln!(
+            out,
+            "shopvac_reconcile_total{{result=\"error\"}} {}",
+            counts.failed
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP shopvac_reconcile_duration_seconds Time taken by a reconcile."
+        );
+        let _ = writeln!(out, "# TYPE shopvac_reconcile_duration_seconds histogram");
+        let mut cumulative = 0;
+        for (i, le) in DURATION_BUCKETS.iter().enumerate() {
+            cumulative += counts.duration_buckets.get(i).copied().unwrap_or(0);
+            let _ = writeln!(
+                out,
+                "shopvac_reconcile_duration_seconds_bucket{{le=\"{}\"}} {}",
+                le, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "shopvac_reconcile_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            counts.reconciled
+        );
+        let _ = writeln!(
+            out,
+            "shopvac_reconcile_duration_seconds_sum {}",
+            counts.duration_sum
+        );
+        let _ = writeln!(
+            out,
+            "shopvac_reconcile_duration_seconds_count {}",
+            counts.reconciled
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP shopvac_podcleaners PodCleaners the controller is watching."
+        );
+        let _ = writeln!(out, "# TYPE shopvac_podcleaners gauge");
+        let _ = writeln!(out, "shopvac_podcleaners {}", podcleaners);
+
+        let _ = writeln!(
+            out,
+            "# HELP shopvac_child_apply_errors_total Child objects that couldn't be applied, by kind."
+        );
+        let _ = writeln!(out, "# TYPE shopvac_child_apply_errors_total counter");
+        for kind in ["CronJob", "ServiceAccount", "Role", "RoleBinding"] {
+            let _ = writeln!(
+                out,
+                "shopvac_child_apply_errors_total{{kind=\"{}\"}} {}",
+                kind,
+                counts.child_apply_errors.get(kind).copied().unwrap_or(0)
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP shopvac_watch_errors_total Failed watches of PodCleaners and their CronJobs."
+        );
+        let _ = writeln!(out, "# TYPE shopvac_watch_errors_total counter");
+        let _ = writeln!(out, "shopvac_watch_errors_total {}", counts.watch_errors);
+        out
+    }
+}
+
+/// Binds `addr` right away, so a port that is taken fails startup, and
+/// returns the server to spawn. It serves `/metrics` until the process
+/// exits, and `/healthz`, which fails once the watch has been failing for
+/// `unhealthy_after`.
+pub fn serve(
+    addr: SocketAddr,
+    metrics: Arc<Metrics>,
+    store: Store<PodCleaner>,
+    unhealthy_after: Duration,
+) -> hyper::Result<impl Future<Output = ()>> {
+    let make_service = make_service_fn(move |_| {
+        let (metrics, store) = (metrics.clone(), store.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let response = match request.uri().path() {
+                    "/metrics" => Response::builder()
+                        .header("content-type", "text/plain; version=0.0.4")
+                        .body(Body::from(metrics.render(store.state().len()))),
+                    "/healthz" => {
+                        match metrics.watch_failing_for(unhealthy_after, Instant::now()) {
+                            Some(failing) => Response::builder()
+                                .status(StatusCode::SERVICE_UNAVAILABLE)
+                                .body(Body::from(format!(
+                                    "watch failing for {}s\n",
+                                    failing.as_secs()
+                                ))),
+                            None => Response::builder().body(Body::from("ok\n")),
+                        }
+                    }
+                    _ => Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::empty()),
+                };
+                async move { response }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    tracing::info!("Serving metrics on {}/metrics", addr);
+    Ok(async move {
+        if let Err(e) = server.await {
+            tracing::error!("Metrics server failed: {}", e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use kube::runtime::reflector::store::Writer;
+
+    use super::{serve, Metrics};
+    use crate::PodCleaner;
+
+    #[tokio::test]
+    async fn taken_port_fails_to_serve() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let served = serve(
+            taken.local_addr().unwrap(),
+            Arc::new(Metrics::default()),
+            Writer::<PodCleaner>::default().as_reader(),
+            Duration::from_secs(300),
+        );
+        assert!(served.is_err());
+    }
+
+    #[test]
+    fn renders_counters_and_histogram() {
+        let metrics = Metrics::default();
+        metrics.reconciled(Duration::from_millis(30));
+        metrics.reconciled(Duration::from_secs(3));
+        metrics.failed();
+        metrics.child_apply_failed("CronJob");
+
+        let rendered = metrics.render(2);
+        assert!(rendered.contains("shopvac_reconcile_total{result=\"ok\"} 1\n"));
+        assert!(rendered.contains("shopvac_reconcile_total{result=\"error\"} 1\n"));
+        assert!(rendered.contains("shopvac_reconcile_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+        assert!(rendered.contains("shopvac_reconcile_duration_seconds_bucket{le=\"5\"} 2\n"));
+        assert!(rendered.contains("shopvac_reconcile_duration_seconds_count 2\n"));
+        assert!(rendered.contains("shopvac_podcleaners 2\n"));
+        assert!(rendered.contains("shopvac_child_apply_errors_total{kind=\"CronJob\"} 1\n"));
+        assert!(rendered.contains("shopvac_child_apply_errors_total{kind=\"RoleBinding\"} 0\n"));
+        assert!(rendered.contains("shopvac_watch_errors_total 0\n"));
+    }
+
+    #[test]
+    fn watch_is_unhealthy_after_failing_continuously() {
+        let metrics = Metrics::default();
+        let window = Duration::from_secs(5 * 60);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        for secs in (0..=360).step_by(60) {
+            metrics.watch_failed("PodCleaner", at(secs));
+        }
+        assert_eq!(metrics.watch_failing_for(window, at(240)), None);
+        assert_eq!(
+            metrics.watch_failing_for(window, at(370)),
+            Some(Duration::from_secs(370))
+        );
+        assert!(metrics.render(0).contains("shopvac_watch_errors_total 7\n"));
+
+        // errors that stopped coming are over, a late one starts a new run
+        // rather than extending the old one
+        assert_eq!(metrics.watch_failing_for(window, at(600)), None);
+        metrics.watch_failed("PodCleaner", at(1000));
+        assert_eq!(metrics.watch_failing_for(window, at(1010)), None);
+
+        // and the watch delivering again ends the run
+        for secs in (1060..=1400).step_by(60) {
+            metrics.watch_failed("PodCleaner", at(secs));
+        }
+        assert!(metrics.watch_failing_for(window, at(1400)).is_some());
+        metrics.watch_recovered("PodCleaner");
+        assert_eq!(metrics.watch_failing_for(window, at(1400)), None);
+
+        // nor does one watch recovering end the run of another
+        for secs in (1460..=1800).step_by(60) {
+            metrics.watch_failed("PodCleaner", at(secs));
+            metrics.watch_failed("CronJob", at(secs));
+        }
+        metrics.watch_recovered("CronJob");
+        assert!(metrics.watch_failing_for(window, at(1800)).is_some());
+    }
+}
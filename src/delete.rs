@@ -0,0 +1,616 @@
+//! Issuing the deletes for scanned pods.
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{
+    future::{Future, FutureExt},
+    stream::{self, Stream, StreamExt},
+};
+use kube::{
+    api::{Api, DeleteParams, Resource, ResourceExt},
+    Client,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::time::{self, Instant};
+
+use crate::scan::PodTarget;
+
+/// What happened to a pod we selected for deletion.
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    /// The apiserver removed the object right away
+    Deleted,
+    /// The delete was accepted and the object is shutting down, e.g. a pod in
+    /// its grace period or waiting on finalizers
+    Terminating,
+    /// Something else deleted the object before we got to it
+    AlreadyGone,
+    Failed {
+        error: String,
+        /// HTTP status the apiserver answered with, if it answered at all
+        code: Option<u16>,
+    },
+    Skipped {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct DeleteOptions {
+    pub params: DeleteParams,
+    /// How many deletes are in flight at once
+    pub concurrency: usize,
+    /// Most deletes started per second, regardless of how quickly the
+    /// in-flight ones complete
+    pub rate: Option<u32>,
+    /// Deletes issued in one go before pausing for `batch_delay`, each batch
+    /// finishes before the next one starts
+    pub batch_size: Option<usize>,
+    pub batch_delay: Duration,
+}
+
+impl Outcome {
+    /// Whether our delete took, now or once the object finishes terminating.
+    pub fn is_deleted(&self) -> bool {
+        matches!(self, Outcome::Deleted | Outcome::Terminating)
+    }
+}
+
+impl Default for DeleteOptions {
+    fn default() -> Self {
+        // buffered 10 at a time as to not overwhelm the kubeapi server
+        Self {
+            params: DeleteParams::default(),
+            concurrency: 10,
+            rate: None,
+            batch_size: None,
+            batch_delay: Duration::ZERO,
+        }
+    }
+}
+
+/// The outcome of every target handed to [`delete_pods`].
+#[derive(Debug)]
+pub struct DeleteReport<'a, T = PodTarget> {
+    pub outcomes: Vec<(&'a T, Outcome)>,
+    /// How long the apiserver took to answer each delete that was sent, in
+    /// completion order
+    pub timings: Vec<(&'a T, Duration)>,
+    /// Wall time of the whole sweep, including any rate limiting
+    pub elapsed: Duration,
+}
+
+impl<'a, T> DeleteReport<'a, T> {
+    /// The `n` slowest deletes, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<(&'a T, Duration)> {
+        let mut timings = self.timings.clone();
+        timings.sort_by(|a, b| b.1.cmp(&a.1));
+        timings.truncate(n);
+        timings
+    }
+}
+
+/// Deletes `targets` in order, until they run out or `shutdown` resolves.
+///
+/// Once `shutdown` fires no new deletes are started, the ones in flight are
+/// still driven to completion and the rest are reported as skipped.
+///
+/// note: this will return instantly, it does not wait for finalizers!
+pub async fn delete_pods<'a>(
+    client: Client,
+    targets: &'a [PodTarget],
+    options: DeleteOptions,
+    shutdown: impl Future<Output = ()>,
+) -> DeleteReport<'a> {
+    delete_each(client, targets, |t| &t.pod, (), options, shutdown).await
+}
+
+/// Deletes whole objects of any kind, e.g. what [`crate::scan_replicasets`]
+/// found, the same way [`delete_pods`] deletes pods.
+///
+/// `dyntype` is `()` for the k8s-openapi types, and the `ApiResource` of a
+/// [`kube::api::DynamicObject`], see [`crate::dynamic`].
+pub async fn delete_objects<'a, K>(
+    client: Client,
+    objects: &'a [K],
+    dyntype: K::DynamicType,
+    options: DeleteOptions,
+    shutdown: impl Future<Output = ()>,
+) -> DeleteReport<'a, K>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    delete_each(client, objects, |o| o, dyntype, options, shutdown).await
+}
+
+/// Deletes pods as they arrive from `targets`, e.g. straight off a scan, with
+/// the same concurrency, rate limit and shutdown handling as [`delete_pods`].
+///
+/// Yields each target with its outcome as the delete completes. Once
+/// `shutdown` fires no more targets are pulled from `targets`, those are
+/// never seen and so can't be reported.
+pub fn delete_stream(
+    client: Client,
+    targets: impl Stream<Item = PodTarget>,
+    options: DeleteOptions,
+    shutdown: impl Future<Output = ()>,
+) -> impl Stream<Item = (PodTarget, Outcome)> {
+    issue(
+        client,
+        targets,
+        |t: &PodTarget| &t.pod,
+        (),
+        options,
+        shutdown,
+    )
+    .map(|(t, outcome, _)| (t, outcome))
+}
+
+/// Shared by every resource shopvac can clean, `object` picks the Kubernetes
+/// object out of a target.
+pub(crate) async fn delete_each<'a, T, K>(
+    client: Client,
+    targets: &'a [T],
+    object: impl Fn(&T) -> &K,
+    dyntype: K::DynamicType,
+    options: DeleteOptions,
+    shutdown: impl Future<Output = ()>,
+) -> DeleteReport<'a, T>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let started = Instant::now();
+    let deletions = issue(
+        client,
+        stream::iter(targets),
+        |t: &&T| object(t),
+        dyntype,
+        options,
+        shutdown,
+    );
+    futures::pin_mut!(deletions);
+
+    let mut outcomes = Vec::with_capacity(targets.len());
+    let mut timings = Vec::with_capacity(targets.len());
+    while let Some((target, outcome, took)) = deletions.next().await {
+        timings.push((target, took));
+        outcomes.push((target, outcome));
+    }
+
+    // deletes are issued in order, so anything past what we got back was
+    // never sent to the apiserver
+    let attempted = outcomes.len();
+    for target in &targets[attempted..] {
+        outcomes.push((
+            target,
+            Outcome::Skipped {
+                reason: "interrupted".to_string(),
+            },
+        ));
+    }
+
+    DeleteReport {
+        outcomes,
+        timings,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Sends a delete for each target, yielding the outcome and how long the
+/// apiserver took to answer as each one completes.
+fn issue<T, K>(
+    client: Client,
+    targets: impl Stream<Item = T>,
+    object: impl Fn(&T) -> &K,
+    dyntype: K::DynamicType,
+    options: DeleteOptions,
+    shutdown: impl Future<Output = ()>,
+) -> impl Stream<Item = (T, Outcome, Duration)>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let DeleteOptions {
+        params,
+        concurrency,
+        rate,
+        batch_size,
+        batch_delay,
+    } = options;
+    // also cuts a pause between batches short
+    let shutdown = shutdown.shared();
+    let kind = K::kind(&dyntype).to_lowercase();
+
+    // one tick per delete when rate limited, `Delay` so a stall doesn't bank
+    // up ticks that are then spent in a burst
+    let ticks = stream::unfold(
+        rate.map(|rate| {
+            let mut interval = time::interval(time::Duration::from_secs_f64(1.0 / rate as f64));
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            interval
+        }),
+        |mut interval| async move {
+            if let Some(interval) = interval.as_mut() {
+                interval.tick().await;
+            }
+            Some(((), interval))
+        },
+    );
+
+    // each object is deleted through its own namespace, an `Api::all` can
+    // list across namespaces but can't delete by bare name
+    let deletes = targets
+        .zip(ticks)
+        .map(|(t, _)| t)
+        .take_until(shutdown.clone())
+        .map(move |t| {
+            let obj = object(&t);
+            let name = obj.name();
+            tracing::debug!("Deleting {}: {}", kind, name);
+            let api: Api<K> = Api::namespaced_with(
+                client.clone(),
+                &obj.namespace().unwrap_or_default(),
+                &dyntype,
+            );
+            let params = params.clone();
+            let kind = kind.clone();
+            async move {
+                let sent = Instant::now();
+                let res = api.delete(&name, &params).await;
+                let took = sent.elapsed();
+                // the apiserver answers with the object while it is still
+                // terminating, and with a Status once it is gone
+                let outcome = match res.map(|r| r.left()) {
+                    Ok(Some(obj)) if obj.meta().deletion_timestamp.is_some() => {
+                        tracing::debug!("Deletion of {} {} accepted, terminating", kind, name);
+                        Outcome::Terminating
+                    }
+                    Ok(_) => Outcome::Deleted,
+                    Err(kube::Error::Api(response)) if response.code == 404 => {
+                        tracing::info!("{} {} was already gone", kind, name);
+                        Outcome::AlreadyGone
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to delete {} {}: {}", kind, name, e);
+                        Outcome::Failed {
+                            code: match &e {
+                                kube::Error::Api(response) => Some(response.code),
+                                _ => None,
+                            },
+                            error: e.to_string(),
+                        }
+                    }
+                };
+                (t, outcome, took)
+            }
+        });
+
+    let size = match batch_size {
+        Some(size) => size,
+        None => return deletes.buffer_unordered(concurrency).left_stream(),
+    };
+    let failed = Arc::new(AtomicUsize::new(0));
+    let counted = failed.clone();
+    deletes
+        .chunks(size)
+        .enumerate()
+        .then(move |(done, batch)| {
+            let (shutdown, failed) = (shutdown.clone(), failed.clone());
+            async move {
+                if done > 0 {
+                    tracing::info!(
+                        "Batch {} done, {} deletes sent so far, {} failed, pausing {:?}",
+                        done,
+                        done * size,
+                        failed.load(Ordering::Relaxed),
+                        batch_delay
+                    );
+                    // a batch pulled but not started counts as never sent
+                    tokio::select! {
+                        _ = time::sleep(batch_delay) => {}
+                        _ = shutdown => return Vec::new(),
+                    }
+                }
+                batch
+            }
+        })
+        .flat_map(move |batch| stream::iter(batch).buffer_unordered(concurrency))
+        .inspect(move |(_, outcome, _)| {
+            if matches!(outcome, Outcome::Failed { .. }) {
+                counted.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .right_stream()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, Duration};
+    use futures::StreamExt;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+    use serde_json::json;
+
+    use super::{delete_pods, delete_stream, DeleteOptions, DeleteReport, Outcome};
+    use crate::{
+        testing::{json_response, mock_client, pod},
+        PodTarget,
+    };
+
+    fn target(namespace: &str, name: &str) -> PodTarget {
+        PodTarget {
+            pod: pod(
+                namespace,
+                name,
+                Some(offset::Utc::now() - Duration::days(30)),
+            ),
+            reason: String::new(),
+        }
+    }
+
+    fn status(code: u16, reason: &str) -> serde_json::Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Status",
+            "metadata": {},
+            "status": "Failure",
+            "message": reason,
+            "reason": reason,
+            "code": code,
+        })
+    }
+
+    #[tokio::test]
+    async fn deletes_each_pod_in_its_own_namespace() {
+        let (client, mut handle) = mock_client();
+        let targets = vec![target("team-a", "build"), target("team-b", "build")];
+
+        let server = tokio::spawn(async move {
+            let mut paths = Vec::new();
+            for _ in 0..2 {
+                let (request, send) = handle.next_request().await.expect("delete not sent");
+                assert_eq!(request.method(), http::Method::DELETE);
+                paths.push(request.uri().path().to_string());
+                send.send_response(json_response(200, json!(pod("x", "x", None))));
+            }
+            paths.sort();
+            paths
+        });
+
+        let report = delete_pods(
+            client,
+            &targets,
+            DeleteOptions::default(),
+            futures::future::pending(),
+        )
+        .await;
+        let paths = server.await.unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                "/api/v1/namespaces/team-a/pods/build",
+                "/api/v1/namespaces/team-b/pods/build",
+            ]
+        );
+        assert!(report
+            .outcomes
+            .iter()
+            .all(|(_, o)| matches!(o, Outcome::Deleted)));
+    }
+
+    #[tokio::test]
+    async fn classifies_delete_responses() {
+        let (client, mut handle) = mock_client();
+        let targets = vec![
+            target("apps", "ok"),
+            target("apps", "terminating"),
+            target("apps", "missing"),
+            target("apps", "forbidden"),
+        ];
+
+        let server = tokio::spawn(async move {
+            for _ in 0..4 {
+                let (request, send) = handle.next_request().await.expect("delete not sent");
+                let response = match request.uri().path().rsplit('/').next() {
+                    Some("ok") => json_response(200, json!(pod("apps", "ok", None))),
+                    Some("terminating") => {
+                        let mut terminating = pod("apps", "terminating", None);
+                        terminating.metadata.deletion_timestamp = Some(Time(offset::Utc::now()));
+                        json_response(200, json!(terminating))
+                    }
+                    Some("missing") => json_response(404, status(404, "NotFound")),
+                    _ => json_response(403, status(403, "Forbidden")),
+                };
+                send.send_response(response);
+            }
+        });
+
+        let report = delete_pods(
+            client,
+            &targets,
+            DeleteOptions::default(),
+            futures::future::pending(),
+        )
+        .await;
+        server.await.unwrap();
+
+        let outcome = |name: &str| {
+            &report
+                .outcomes
+                .iter()
+                .find(|(t, _)| t.name() == name)
+                .unwrap()
+                .1
+        };
+        assert!(matches!(outcome("ok"), Outcome::Deleted));
+        assert!(matches!(outcome("terminating"), Outcome::Terminating));
+        assert!(matches!(outcome("missing"), Outcome::AlreadyGone));
+        assert!(matches!(
+            outcome("forbidden"),
+            Outcome::Failed {
+                code: Some(403),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn shutdown_skips_everything_not_yet_sent() {
+        let (client, _handle) = mock_client();
+        let targets = vec![target("apps", "a"), target("apps", "b")];
+
+        let report = delete_pods(
+            client,
+            &targets,
+            DeleteOptions::default(),
+            futures::future::ready(()),
+        )
+        .await;
+
+        assert_eq!(report.outcomes.len(), 2);
+        assert!(report
+            .outcomes
+            .iter()
+            .all(|(_, o)| matches!(o, Outcome::Skipped { reason } if reason == "interrupted")));
+    }
+
+    #[tokio::test]
+    async fn shutdown_during_a_batch_pause_skips_the_next_batch() {
+        let (client, mut handle) = mock_client();
+        let targets = vec![
+            target("apps", "a"),
+            target("apps", "b"),
+            target("apps", "c"),
+        ];
+
+        let server = tokio::spawn(async move {
+            let mut deleted = Vec::new();
+            while let Some((request, send)) = handle.next_request().await {
+                let name = request.uri().path().rsplit('/').next().unwrap().to_string();
+                send.send_response(json_response(200, json!(pod("apps", &name, None))));
+                deleted.push(name);
+            }
+            deleted
+        });
+
+        let options = DeleteOptions {
+            batch_size: Some(2),
+            batch_delay: std::time::Duration::from_secs(60),
+            ..DeleteOptions::default()
+        };
+        let report = delete_pods(
+            client,
+            &targets,
+            options,
+            tokio::time::sleep(std::time::Duration::from_millis(200)),
+        )
+        .await;
+
+        let mut deleted = server.await.unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec!["a", "b"]);
+        assert!(report.elapsed < std::time::Duration::from_secs(30));
+        let outcome = |name: &str| {
+            &report
+                .outcomes
+                .iter()
+                .find(|(t, _)| t.name() == name)
+                .unwrap()
+                .1
+        };
+        assert!(matches!(outcome("a"), Outcome::Deleted));
+        assert!(matches!(outcome("b"), Outcome::Deleted));
+        assert!(matches!(outcome("c"), Outcome::Skipped { reason } if reason == "interrupted"));
+    }
+
+    #[tokio::test]
+    async fn streamed_and_planned_deletes_agree() {
+        let (client, mut handle) = mock_client();
+        let targets = vec![
+            target("apps", "ok"),
+            target("apps", "missing"),
+            target("apps", "forbidden"),
+        ];
+
+        let server = tokio::spawn(async move {
+            // once for each mode
+            for _ in 0..6 {
+                let (request, send) = handle.next_request().await.expect("delete not sent");
+                let response = match request.uri().path().rsplit('/').next() {
+                    Some("ok") => json_response(200, json!(pod("apps", "ok", None))),
+                    Some("missing") => json_response(404, status(404, "NotFound")),
+                    _ => json_response(403, status(403, "Forbidden")),
+                };
+                send.send_response(response);
+            }
+        });
+
+        let summarize = |outcomes: Vec<(String, &Outcome)>| {
+            let mut summary: Vec<(String, Option<u16>)> = outcomes
+                .into_iter()
+                .map(|(name, o)| match o {
+                    Outcome::Failed { code, .. } => (name, *code),
+                    _ => (name, None),
+                })
+                .collect();
+            summary.sort();
+            summary
+        };
+
+        let planned = delete_pods(
+            client.clone(),
+            &targets,
+            DeleteOptions::default(),
+            futures::future::pending(),
+        )
+        .await;
+        let streamed: Vec<(PodTarget, Outcome)> = delete_stream(
+            client,
+            futures::stream::iter(targets.clone()),
+            DeleteOptions::default(),
+            futures::future::pending(),
+        )
+        .collect()
+        .await;
+        server.await.unwrap();
+
+        assert_eq!(
+            summarize(
+                planned
+                    .outcomes
+                    .iter()
+                    .map(|(t, o)| (t.name(), o))
+                    .collect()
+            ),
+            summarize(streamed.iter().map(|(t, o)| (t.name(), o)).collect())
+        );
+    }
+
+    #[test]
+    fn slowest_sorts_descending_and_truncates() {
+        let targets = vec![
+            target("apps", "a"),
+            target("apps", "b"),
+            target("apps", "c"),
+        ];
+        let ms = std::time::Duration::from_millis;
+        let report = DeleteReport {
+            outcomes: Vec::new(),
+            timings: vec![
+                (&targets[0], ms(20)),
+                (&targets[1], ms(300)),
+                (&targets[2], ms(5)),
+            ],
+            elapsed: ms(310),
+        };
+
+        let slowest: Vec<String> = report.slowest(2).iter().map(|(t, _)| t.name()).collect();
+        assert_eq!(slowest, vec!["b", "a"]);
+    }
+}
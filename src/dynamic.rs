@@ -0,0 +1,224 @@
+//! Any namespaced resource picked by group, version and kind at runtime, for
+//! the kinds shopvac has no dedicated scan for.
+use std::{fmt, str::FromStr};
+
+use chrono::offset;
+use kube::{
+    api::{Api, ApiResource, DynamicObject, GroupVersionKind, TypeMeta},
+    discovery::{self, Scope},
+    Client,
+};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    error::{Result, ShopvacError},
+    filter::object_selected,
+    scan::ScanOptions,
+};
+
+/// A resource type given as `group/version/kind`, or `version/kind` for the
+/// core group, e.g. `batch/v1/Job` or `v1/ConfigMap`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Gvk {
+    pub group: String,
+    pub version: String,
+    pub kind: String,
+}
+
+impl FromStr for Gvk {
+    type Err = ShopvacError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        let (group, version, kind) = match parts[..] {
+            [group, version, kind] => (group, version, kind),
+            [version, kind] => ("", version, kind),
+            _ => ("", "", ""),
+        };
+        if version.is_empty() || kind.is_empty() {
+            return Err(ShopvacError::Validation(format!(
+                "invalid resource type {:?}, expected group/version/kind",
+                s
+            )));
+        }
+        Ok(Self {
+            group: group.to_string(),
+            version: version.to_string(),
+            kind: kind.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for Gvk {
+    type Error = ShopvacError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Gvk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.group.is_empty() {
+            write!(f, "{}/{}", self.version, self.kind)
+        } else {
+            write!(f, "{}/{}/{}", self.group, self.version, self.kind)
+        }
+    }
+}
+
+/// Looks `gvk` up in the apiserver's discovery, for its plural name. Kinds the
+/// cluster doesn't serve and cluster scoped kinds are rejected, shopvac only
+/// ever deletes within namespaces.
+pub async fn discover(client: Client, gvk: &Gvk) -> Result<ApiResource> {
+    let (resource, capabilities) = discovery::pinned_kind(
+        &client,
+        &GroupVersionKind::gvk(&gvk.group, &gvk.version, &gvk.kind),
+    )
+    .await
+    .map_err(|e| match e {
+        kube::Error::Discovery(e) => {
+            ShopvacError::Validation(format!("{} isn't served by the cluster: {}", gvk, e))
+        }
+        e => e.into(),
+    })?;
+    if !matches!(capabilities.scope, Scope::Namespaced) {
+        return Err(ShopvacError::Validation(format!(
+            "{} is cluster scoped, only namespaced resources can be cleaned",
+            gvk
+        )));
+    }
+    Ok(resource)
+}
+
+/// Lists the objects visible through `api` and returns the ones matching
+/// `options`, with their type filled in from `resource` when the list left it
+/// out.
+///
+/// Only the namespace, annotation and age options apply, going by the
+/// creation timestamp since there is no status to look at.
+pub async fn scan_dynamic(
+    api: &Api<DynamicObject>,
+    resource: &ApiResource,
+    options: &ScanOptions,
+) -> Result<Vec<DynamicObject>> {
+    let ns_regex = Regex::new(&options.exclude_namespace_pattern)?;
+
+    let listed = api.list(&options.list_params()).await?;
+
+    let now = offset::Utc::now();
+    Ok(listed
+        .into_iter()
+        .filter(|obj| object_selected(obj, options, &ns_regex, now))
+        .map(|mut obj| {
+            obj.types.get_or_insert_with(|| TypeMeta {
+                api_version: resource.api_version.clone(),
+                kind: resource.kind.clone(),
+            });
+            obj
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, Duration};
+    use kube::api::{Api, DynamicObject};
+    use serde_json::json;
+
+    use super::{discover, scan_dynamic, Gvk};
+    use crate::{
+        testing::{json_response, mock_client, options},
+        ShopvacError,
+    };
+
+    #[test]
+    fn gvk_parses_with_and_without_a_group() {
+        let job: Gvk = "batch/v1/Job".parse().unwrap();
+        assert_eq!(
+            (job.group.as_str(), job.version.as_str(), job.kind.as_str()),
+            ("batch", "v1", "Job")
+        );
+        assert_eq!(job.to_string(), "batch/v1/Job");
+
+        let cm: Gvk = "v1/ConfigMap".parse().unwrap();
+        assert_eq!(cm.group, "");
+        assert_eq!(cm.to_string(), "v1/ConfigMap");
+
+        for invalid in ["Job", "batch/v1/", "a/b/c/d", ""] {
+            assert!(matches!(
+                invalid.parse::<Gvk>(),
+                Err(ShopvacError::Validation(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn old_custom_resources_are_found() {
+        let (client, mut handle) = mock_client();
+        let created = |days| (offset::Utc::now() - Duration::days(days)).to_rfc3339();
+
+        let server = tokio::spawn(async move {
+            while let Some((request, send)) = handle.next_request().await {
+                let widget = |name: &str, days: i64| {
+                    json!({
+                        "metadata": {
+                            "name": name,
+                            "namespace": "apps",
+                            "creationTimestamp": created(days),
+                        },
+                        "spec": {},
+                    })
+                };
+                let response = match request.uri().path() {
+                    "/apis/example.com/v1" => json_response(
+                        200,
+                        json!({
+                            "kind": "APIResourceList",
+                            "groupVersion": "example.com/v1",
+                            "resources": [{
+                                "name": "widgets",
+                                "singularName": "widget",
+                                "namespaced": true,
+                                "kind": "Widget",
+                                "verbs": ["list", "delete"],
+                            }],
+                        }),
+                    ),
+                    "/apis/example.com/v1/namespaces/apps/widgets" => json_response(
+                        200,
+                        json!({
+                            "apiVersion": "example.com/v1",
+                            "kind": "WidgetList",
+                            "metadata": {},
+                            "items": [widget("old", 30), widget("new", 1)],
+                        }),
+                    ),
+                    path => panic!("unexpected request to {}", path),
+                };
+                send.send_response(response);
+            }
+        });
+
+        let gvk: Gvk = "example.com/v1/Widget".parse().unwrap();
+        let resource = discover(client.clone(), &gvk).await.unwrap();
+        assert_eq!(resource.plural, "widgets");
+        let api: Api<DynamicObject> = Api::namespaced_with(client, "apps", &resource);
+        let found = scan_dynamic(&api, &resource, &options()).await.unwrap();
+        drop(api);
+        server.await.unwrap();
+
+        let names: Vec<String> = found
+            .iter()
+            .map(|o| o.metadata.name.clone().unwrap())
+            .collect();
+        assert_eq!(names, vec!["old"]);
+        let types = found[0].types.as_ref().unwrap();
+        assert_eq!(
+            (types.api_version.as_str(), types.kind.as_str()),
+            ("example.com/v1", "Widget")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! The ways a scan or cleanup can fail, typed so callers can tell them apart
+//! and the CLI can map them to exit codes.
+use std::{error::Error as StdError, io};
+
+use thiserror::Error;
+
+pub type Result<T, E = ShopvacError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum ShopvacError {
+    /// The apiserver answered 403
+    #[error("Forbidden: {0}")]
+    NoPermission(String),
+    /// A request to the apiserver didn't finish in time
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    /// Options that can never work, e.g. an invalid pattern
+    #[error("{0}")]
+    Validation(String),
+    /// Any other apiserver or client failure
+    #[error(transparent)]
+    Kube(kube::Error),
+}
+
+impl ShopvacError {
+    /// 2 without permission, 3 on a timeout, 4 for invalid options and 1 for
+    /// anything else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ShopvacError::NoPermission(_) => 2,
+            ShopvacError::Timeout(_) => 3,
+            ShopvacError::Validation(_) => 4,
+            ShopvacError::Kube(_) => 1,
+        }
+    }
+}
+
+/// The exit code for a failure, going by the first [`ShopvacError`] or
+/// [`kube::Error`] in its chain of causes, 1 when there is neither.
+pub fn exit_code<'a>(chain: impl IntoIterator<Item = &'a (dyn StdError + 'static)>) -> i32 {
+    chain
+        .into_iter()
+        .find_map(|cause| {
+            if let Some(e) = cause.downcast_ref::<ShopvacError>() {
+                Some(e.exit_code())
+            } else {
+                cause.downcast_ref::<kube::Error>().map(|e| {
+                    if forbidden(e).is_some() {
+                        2
+                    } else if timed_out(e) {
+                        3
+                    } else {
+                        1
+                    }
+                })
+            }
+        })
+        .unwrap_or(1)
+}
+
+fn forbidden(e: &kube::Error) -> Option<&str> {
+    match e {
+        kube::Error::Api(response) if response.code == 403 => Some(&response.message),
+        _ => None,
+    }
+}
+
+/// Whether the client gave up waiting, which surfaces as an I/O timeout
+/// somewhere down the chain rather than as its own variant.
+fn timed_out(e: &kube::Error) -> bool {
+    let mut cause: Option<&(dyn StdError + 'static)> = Some(e);
+    while let Some(e) = cause {
+        if matches!(e.downcast_ref::<io::Error>(), Some(e) if e.kind() == io::ErrorKind::TimedOut) {
+            return true;
+        }
+        cause = e.source();
+    }
+    false
+}
+
+impl From<kube::Error> for ShopvacError {
+    fn from(e: kube::Error) -> Self {
+        if let Some(message) = forbidden(&e) {
+            ShopvacError::NoPermission(message.to_string())
+        } else if timed_out(&e) {
+            ShopvacError::Timeout(e.to_string())
+        } else {
+            ShopvacError::Kube(e)
+        }
+    }
+}
+
+impl From<regex::Error> for ShopvacError {
+    fn from(e: regex::Error) -> Self {
+        ShopvacError::Validation(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error as StdError, io};
+
+    use kube::core::ErrorResponse;
+
+    use super::{exit_code, ShopvacError};
+
+    fn api_error(code: u16) -> kube::Error {
+        kube::Error::Api(ErrorResponse {
+            status: "Failure".to_string(),
+            message: format!("status {}", code),
+            reason: String::new(),
+            code,
+        })
+    }
+
+    #[test]
+    fn kube_errors_are_classified() {
+        let forbidden = ShopvacError::from(api_error(403));
+        assert!(matches!(&forbidden, ShopvacError::NoPermission(m) if m == "status 403"));
+        assert_eq!(forbidden.exit_code(), 2);
+
+        let timeout = ShopvacError::from(kube::Error::ReadEvents(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "deadline has elapsed",
+        )));
+        assert!(matches!(timeout, ShopvacError::Timeout(_)));
+        assert_eq!(timeout.exit_code(), 3);
+
+        let pattern = ShopvacError::from(regex::Regex::new("(").unwrap_err());
+        assert_eq!(pattern.exit_code(), 4);
+
+        let other = ShopvacError::from(api_error(500));
+        assert!(matches!(other, ShopvacError::Kube(_)));
+        assert_eq!(other.exit_code(), 1);
+    }
+
+    #[test]
+    fn exit_code_looks_through_the_chain() {
+        let cause = ShopvacError::Timeout("listing pods".to_string());
+        let wrapped = io::Error::new(io::ErrorKind::Other, "scan failed");
+        let chain = [
+            &wrapped as &(dyn StdError + 'static),
+            &cause as &(dyn StdError + 'static),
+        ];
+        assert_eq!(exit_code(chain), 3);
+
+        let raw = api_error(403);
+        assert_eq!(exit_code([&raw as &(dyn StdError + 'static)]), 2);
+        assert_eq!(exit_code([&wrapped as &(dyn StdError + 'static)]), 1);
+    }
+}
@@ -0,0 +1,897 @@
+//! Client side pod filters, composed into a single predicate that can also
+//! explain which condition decided a pod.
+use std::{fmt, ops, str::FromStr};
+
+use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{Resource, ResourceExt};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{error::Result, format_age, scan::ScanOptions};
+
+/// Namespaces that are never cleaned unless --allow-system-namespaces is
+/// given, independent of --exclude-namespace-pattern.
+const SYSTEM_NAMESPACES: &[&str] = &["kube-system", "kube-public", "kube-node-lease"];
+const SYSTEM_NAMESPACE_PREFIXES: &[&str] = &["openshift-"];
+
+pub fn is_system_namespace(ns: &str) -> bool {
+    SYSTEM_NAMESPACES.contains(&ns) || SYSTEM_NAMESPACE_PREFIXES.iter().any(|p| ns.starts_with(p))
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum PodPhase {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Unknown,
+}
+
+impl PodPhase {
+    /// The phase as it appears in `status.phase`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PodPhase::Pending => "Pending",
+            PodPhase::Running => "Running",
+            PodPhase::Succeeded => "Succeeded",
+            PodPhase::Failed => "Failed",
+            PodPhase::Unknown => "Unknown",
+        }
+    }
+}
+
+/// Client side annotation match, since the apiserver has no annotation
+/// selector equivalent to `--label-selector`.
+#[derive(Clone, Debug)]
+pub struct AnnotationSelector {
+    key: String,
+    value: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, thiserror::Error)]
+#[error("invalid annotation selector, expected `key` or `key=value`")]
+pub struct InvalidAnnotationSelector;
+
+impl FromStr for AnnotationSelector {
+    type Err = InvalidAnnotationSelector;
+
+    fn from_str(s: &str) -> Result<Self, InvalidAnnotationSelector> {
+        let (key, value) = match s.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (s, None),
+        };
+        if key.is_empty() {
+            return Err(InvalidAnnotationSelector);
+        }
+        Ok(Self {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+impl fmt::Display for AnnotationSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.key, value),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+impl AnnotationSelector {
+    pub fn matches(&self, obj: &impl ResourceExt) -> bool {
+        match (obj.annotations().get(&self.key), &self.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// The options that apply to any kind of object rather than just pods: the
+/// namespace, annotation and age ones.
+pub(crate) fn object_selected(
+    obj: &impl ResourceExt,
+    options: &ScanOptions,
+    ns_regex: &Regex,
+    now: DateTime<Utc>,
+) -> bool {
+    let ns = obj.namespace().unwrap_or_default();
+    if is_system_namespace(&ns) && !options.allow_system_namespaces {
+        return false;
+    }
+    if ns_regex.is_match(&ns) {
+        return false;
+    }
+    if !options.annotation_selectors.iter().all(|s| s.matches(obj)) {
+        return false;
+    }
+    if let Some(key) = &options.protection_annotation {
+        if matches!(obj.annotations().get(key), Some(value) if value != "false") {
+            return false;
+        }
+    }
+    obj.meta().creation_timestamp.as_ref().map_or(false, |ct| {
+        (now - ct.0).num_hours() > options.older_than.num_hours()
+    })
+}
+
+/// A condition on a single pod. Leaves look at one aspect of the pod, `All`,
+/// `Any` and `Not` combine them.
+#[derive(Clone, Debug)]
+pub enum PodPredicate {
+    /// The namespace is one of the built in system namespaces
+    SystemNamespace,
+    /// The namespace matches the pattern
+    NamespaceMatches(Regex),
+    /// The pod was created longer ago than this
+    OlderThan(Duration),
+    /// The pod was created after this
+    CreatedAfter(DateTime<Utc>),
+    /// The pod's last container finished longer ago than this
+    CompletedFor(Duration),
+    /// The pod carries the annotation, with the value if one is given
+    Annotation(AnnotationSelector),
+    /// `status.phase` is one of these
+    PhaseIn(Vec<PodPhase>),
+    /// `status.reason` is one of these, e.g. `Evicted`
+    ReasonIn(Vec<String>),
+    /// Any container image matches the pattern
+    ImageMatches(Regex),
+    /// The pod name matches the pattern
+    NameMatches(Regex),
+    /// An owner reference is of one of these kinds
+    OwnerKindIn(Vec<String>),
+    /// The pod has no owner reference at all
+    Unowned,
+    /// The annotation is present and not set to `"false"`
+    ProtectedBy(String),
+    /// The pod is Running and Ready, and started longer ago than this
+    RunningHealthyFor(Duration),
+    All(Vec<PodPredicate>),
+    Any(Vec<PodPredicate>),
+    Not(Box<PodPredicate>),
+}
+
+impl ops::Not for PodPredicate {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        PodPredicate::Not(Box::new(self))
+    }
+}
+
+/// The result of evaluating a predicate against a pod.
+#[derive(Clone, Debug)]
+pub struct Verdict {
+    pub matched: bool,
+    /// Name of the leaf predicate that decided the result
+    pub predicate: &'static str,
+    /// Human readable account of what that predicate saw on the pod
+    pub reason: String,
+}
+
+impl fmt::Display for Verdict {
+    /// `selected: <reason>` or `skipped: <reason>`, as `--explain` logs it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = if self.matched { "selected" } else { "skipped" };
+        write!(f, "{}: {}", outcome, self.reason)
+    }
+}
+
+impl PodPredicate {
+    /// Builds the predicate a pod has to satisfy to be cleaned up.
+    pub fn from_options(options: &ScanOptions) -> Result<Self> {
+        let excluded =
+            PodPredicate::NamespaceMatches(Regex::new(&options.exclude_namespace_pattern)?);
+        let mut all = vec![if options.allow_system_namespaces {
+            !excluded
+        } else {
+            !PodPredicate::Any(vec![PodPredicate::SystemNamespace, excluded])
+        }];
+        all.extend(
+            options
+                .annotation_selectors
+                .iter()
+                .cloned()
+                .map(PodPredicate::Annotation),
+        );
+        if !options.phases.is_empty() {
+            all.push(PodPredicate::PhaseIn(options.phases.clone()));
+        }
+        if !options.reasons.is_empty() {
+            all.push(PodPredicate::ReasonIn(options.reasons.clone()));
+        }
+        if let Some(pattern) = &options.image_pattern {
+            all.push(PodPredicate::ImageMatches(Regex::new(pattern)?));
+        }
+        if let Some(pattern) = &options.exclude_pod_pattern {
+            all.push(!PodPredicate::NameMatches(Regex::new(pattern)?));
+        }
+        if !options.owner_kinds.is_empty() {
+            all.push(PodPredicate::OwnerKindIn(options.owner_kinds.clone()));
+        }
+        if options.naked_only {
+            all.push(PodPredicate::Unowned);
+        }
+        if let Some(key) = &options.protection_annotation {
+            all.push(!PodPredicate::ProtectedBy(key.clone()));
+        }
+        if let Some(running_for) = options.exclude_running_longer_than {
+            all.push(!PodPredicate::RunningHealthyFor(running_for));
+        }
+        if let Some(after) = options.created_after {
+            all.push(PodPredicate::CreatedAfter(after));
+        }
+        // a pod with one of the reasons is done for whatever its age, and
+        // the recent evictions are the ones piling up
+        if !options.reasons.is_empty() {
+            return Ok(PodPredicate::All(all));
+        }
+        let older_than = PodPredicate::OlderThan(options.older_than);
+        match options.completed_for {
+            // a pod created long ago may only just have finished, go by the
+            // completion age for those that are done
+            Some(completed_for) => {
+                let terminal =
+                    || PodPredicate::PhaseIn(vec![PodPhase::Succeeded, PodPhase::Failed]);
+                all.push(PodPredicate::Any(vec![
+                    PodPredicate::All(vec![terminal(), PodPredicate::CompletedFor(completed_for)]),
+                    PodPredicate::All(vec![!terminal(), older_than]),
+                ]));
+            }
+            None => all.push(older_than),
+        }
+        Ok(PodPredicate::All(all))
+    }
+
+    /// Short, stable name used when reporting which predicate decided a pod.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PodPredicate::SystemNamespace => "system-namespace",
+            PodPredicate::NamespaceMatches(_) => "namespace-pattern",
+            PodPredicate::OlderThan(_) => "age",
+            PodPredicate::CreatedAfter(_) => "since-last-run",
+            PodPredicate::CompletedFor(_) => "completed-for",
+            PodPredicate::Annotation(_) => "annotation-selector",
+            PodPredicate::PhaseIn(_) => "phase",
+            PodPredicate::ReasonIn(_) => "reason",
+            PodPredicate::ImageMatches(_) => "image-pattern",
+            PodPredicate::NameMatches(_) => "pod-pattern",
+            PodPredicate::OwnerKindIn(_) => "owner-kind",
+            PodPredicate::Unowned => "naked-only",
+            PodPredicate::ProtectedBy(_) => "protection-annotation",
+            PodPredicate::RunningHealthyFor(_) => "running-longer-than",
+            PodPredicate::All(_) => "all",
+            PodPredicate::Any(_) => "any",
+            PodPredicate::Not(_) => "not",
+        }
+    }
+
+    /// Whether evaluating needs the pod's spec or status. When it doesn't,
+    /// pods carrying only their metadata are evaluated exactly like full ones.
+    pub fn needs_full_object(&self) -> bool {
+        match self {
+            PodPredicate::PhaseIn(_)
+            | PodPredicate::ReasonIn(_)
+            | PodPredicate::ImageMatches(_)
+            | PodPredicate::CompletedFor(_)
+            | PodPredicate::RunningHealthyFor(_) => true,
+            PodPredicate::All(predicates) | PodPredicate::Any(predicates) => {
+                predicates.iter().any(PodPredicate::needs_full_object)
+            }
+            PodPredicate::Not(predicate) => predicate.needs_full_object(),
+            _ => false,
+        }
+    }
+
+    /// Evaluates the predicate, reporting the leaf that decided the outcome.
+    ///
+    /// `All` reports the first leaf that failed, or every leaf when it
+    /// matched, `Any` the other way round.
+    pub fn evaluate(&self, pod: &Pod, now: DateTime<Utc>) -> Verdict {
+        let leaf = |matched: bool, reason: String| Verdict {
+            matched,
+            predicate: self.name(),
+            reason,
+        };
+        let ns = pod.namespace().unwrap_or_default();
+
+        match self {
+            PodPredicate::SystemNamespace => {
+                let matched = is_system_namespace(&ns);
+                leaf(
+                    matched,
+                    if matched {
+                        format!("namespace {} is a protected system namespace", ns)
+                    } else {
+                        format!("namespace {} is not a system namespace", ns)
+                    },
+                )
+            }
+            PodPredicate::NamespaceMatches(re) => {
+                let matched = re.is_match(&ns);
+                leaf(
+                    matched,
+                    format!(
+                        "namespace {} {} /{}/",
+                        ns,
+                        if matched { "matches" } else { "does not match" },
+                        re
+                    ),
+                )
+            }
+            PodPredicate::OlderThan(threshold) => match &pod.metadata.creation_timestamp {
+                Some(ct) => {
+                    let age = now - ct.0;
+                    // whole hours, matching how --older-than-hours is given
+                    let matched = age.num_hours() > threshold.num_hours();
+                    leaf(
+                        matched,
+                        format!(
+                            "age {} {} {}",
+                            format_age(age),
+                            if matched { ">" } else { "<=" },
+                            format_age(*threshold)
+                        ),
+                    )
+                }
+                None => leaf(false, "no creation timestamp".to_string()),
+            },
+            PodPredicate::CreatedAfter(after) => match &pod.metadata.creation_timestamp {
+                Some(ct) => {
+                    let matched = ct.0 > *after;
+                    leaf(
+                        matched,
+                        format!(
+                            "created {} {}",
+                            if matched { "after" } else { "before" },
+                            after.to_rfc3339()
+                        ),
+                    )
+                }
+                None => leaf(false, "no creation timestamp".to_string()),
+            },
+            PodPredicate::CompletedFor(threshold) => {
+                let finished = pod
+                    .status
+                    .iter()
+                    .flat_map(|s| s.container_statuses.iter().flatten())
+                    .filter_map(|c| c.state.as_ref()?.terminated.as_ref()?.finished_at.as_ref())
+                    .map(|t| t.0)
+                    .max();
+                match finished {
+                    Some(finished) => {
+                        let age = now - finished;
+                        let matched = age > *threshold;
+                        leaf(
+                            matched,
+                            format!(
+                                "completed {} ago, {} {}",
+                                format_age(age),
+                                if matched { ">" } else { "<=" },
+                                format_age(*threshold)
+                            ),
+                        )
+                    }
+                    None => leaf(false, "no container finish time".to_string()),
+                }
+            }
+            PodPredicate::Annotation(selector) => {
+                let matched = selector.matches(pod);
+                leaf(
+                    matched,
+                    format!(
+                        "annotation {} {}",
+                        selector,
+                        if matched { "present" } else { "missing" }
+                    ),
+                )
+            }
+            PodPredicate::PhaseIn(phases) => {
+                let phase = pod
+                    .status
+                    .as_ref()
+                    .and_then(|s| s.phase.as_deref())
+                    .unwrap_or("<unknown>");
+                let matched = phases.iter().any(|want| want.as_str() == phase);
+                let wanted: Vec<&str> = phases.iter().map(|p| p.as_str()).collect();
+                leaf(
+                    matched,
+                    format!(
+                        "phase {} {} [{}]",
+                        phase,
+                        if matched { "in" } else { "not in" },
+                        wanted.join(", ")
+                    ),
+                )
+            }
+            PodPredicate::ReasonIn(reasons) => {
+                match pod.status.as_ref().and_then(|s| s.reason.as_deref()) {
+                    Some(reason) => {
+                        let matched = reasons.iter().any(|want| want == reason);
+                        leaf(
+                            matched,
+                            format!(
+                                "reason {} {} [{}]",
+                                reason,
+                                if matched { "in" } else { "not in" },
+                                reasons.join(", ")
+                            ),
+                        )
+                    }
+                    None => leaf(false, "no status reason".to_string()),
+                }
+            }
+            PodPredicate::ImageMatches(re) => {
+                let image = pod
+                    .spec
+                    .iter()
+                    .flat_map(|s| &s.containers)
+                    .filter_map(|c| c.image.as_deref())
+                    .find(|i| re.is_match(i));
+                match image {
+                    Some(image) => leaf(true, format!("image {} matches /{}/", image, re)),
+                    None => leaf(false, format!("no image matches /{}/", re)),
+                }
+            }
+            PodPredicate::NameMatches(re) => {
+                let name = pod.name();
+                let matched = re.is_match(&name);
+                leaf(
+                    matched,
+                    format!(
+                        "name {} {} /{}/",
+                        name,
+                        if matched { "matches" } else { "does not match" },
+                        re
+                    ),
+                )
+            }
+            PodPredicate::OwnerKindIn(kinds) => {
+                let owners: Vec<&str> = pod
+                    .owner_references()
+                    .iter()
+                    .map(|o| o.kind.as_str())
+                    .collect();
+                let matched = owners.iter().any(|kind| kinds.iter().any(|k| k == kind));
+                leaf(
+                    matched,
+                    if owners.is_empty() {
+                        "no owner".to_string()
+                    } else {
+                        format!(
+                            "owned by [{}] {} [{}]",
+                            owners.join(", "),
+                            if matched { "in" } else { "not in" },
+                            kinds.join(", ")
+                        )
+                    },
+                )
+            }
+            PodPredicate::Unowned => {
+                let owners: Vec<&str> = pod
+                    .owner_references()
+                    .iter()
+                    .map(|o| o.kind.as_str())
+                    .collect();
+                leaf(
+                    owners.is_empty(),
+                    if owners.is_empty() {
+                        "no owner".to_string()
+                    } else {
+                        format!("owned by [{}]", owners.join(", "))
+                    },
+                )
+            }
+            PodPredicate::ProtectedBy(key) => match pod.annotations().get(key) {
+                Some(value) if value != "false" => {
+                    leaf(true, format!("protected by annotation {}={}", key, value))
+                }
+                _ => leaf(false, format!("not protected by annotation {}", key)),
+            },
+            PodPredicate::RunningHealthyFor(threshold) => {
+                let status = pod.status.as_ref();
+                let phase = status
+                    .and_then(|s| s.phase.as_deref())
+                    .unwrap_or("<unknown>");
+                let ready = status
+                    .and_then(|s| s.conditions.as_ref())
+                    .into_iter()
+                    .flatten()
+                    .any(|c| c.type_ == "Ready" && c.status == "True");
+                match status.and_then(|s| s.start_time.as_ref()) {
+                    Some(started) if phase == "Running" && ready => {
+                        let running = now - started.0;
+                        let matched = running > *threshold;
+                        leaf(
+                            matched,
+                            format!(
+                                "running and ready for {}, {} {}",
+                                format_age(running),
+                                if matched { ">" } else { "<=" },
+                                format_age(*threshold)
+                            ),
+                        )
+                    }
+                    _ if phase != "Running" => leaf(false, format!("phase {}", phase)),
+                    _ if !ready => leaf(false, "running but not ready".to_string()),
+                    _ => leaf(false, "no start time".to_string()),
+                }
+            }
+            PodPredicate::All(predicates) => {
+                let mut reasons = Vec::new();
+                for predicate in predicates {
+                    let verdict = predicate.evaluate(pod, now);
+                    if !verdict.matched {
+                        return verdict;
+                    }
+                    reasons.push(verdict.reason);
+                }
+                leaf(true, reasons.join("; "))
+            }
+            PodPredicate::Any(predicates) => {
+                let mut reasons = Vec::new();
+                for predicate in predicates {
+                    let verdict = predicate.evaluate(pod, now);
+                    if verdict.matched {
+                        return verdict;
+                    }
+                    reasons.push(verdict.reason);
+                }
+                leaf(false, reasons.join("; "))
+            }
+            PodPredicate::Not(predicate) => {
+                let verdict = predicate.evaluate(pod, now);
+                Verdict {
+                    matched: !verdict.matched,
+                    ..verdict
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, DateTime, Duration, Utc};
+    use k8s_openapi::{
+        api::core::v1::{
+            ContainerState, ContainerStateTerminated, ContainerStatus, Pod, PodCondition,
+        },
+        apimachinery::pkg::apis::meta::v1::{OwnerReference, Time},
+    };
+
+    use super::{PodPhase, PodPredicate};
+    use crate::testing::{annotated, options, pod};
+
+    fn finished(mut pod: Pod, at: DateTime<Utc>) -> Pod {
+        pod.status
+            .get_or_insert_with(Default::default)
+            .container_statuses = Some(vec![ContainerStatus {
+            name: "main".to_string(),
+            state: Some(ContainerState {
+                terminated: Some(ContainerStateTerminated {
+                    finished_at: Some(Time(at)),
+                    ..ContainerStateTerminated::default()
+                }),
+                ..ContainerState::default()
+            }),
+            ..ContainerStatus::default()
+        }]);
+        pod
+    }
+
+    #[test]
+    fn age_boundary_is_exclusive() {
+        let now = offset::Utc::now();
+        let predicate = PodPredicate::from_options(&options()).unwrap();
+
+        let at = pod("apps", "at", Some(now - Duration::hours(72)));
+        let just_under = pod("apps", "under", Some(now - Duration::minutes(72 * 60 + 59)));
+        let over = pod("apps", "over", Some(now - Duration::hours(73)));
+
+        let verdict = predicate.evaluate(&at, now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "age");
+        assert!(!predicate.evaluate(&just_under, now).matched);
+        assert!(predicate.evaluate(&over, now).matched);
+    }
+
+    #[test]
+    fn missing_creation_timestamp_is_never_selected() {
+        let now = offset::Utc::now();
+        let predicate = PodPredicate::from_options(&options()).unwrap();
+
+        let verdict = predicate.evaluate(&pod("apps", "new", None), now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "age");
+        assert_eq!(verdict.reason, "no creation timestamp");
+    }
+
+    #[test]
+    fn verdicts_explain_themselves() {
+        let now = offset::Utc::now();
+        let mut opts = options();
+        opts.phases = vec![PodPhase::Failed];
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let mut running = pod("apps", "running", Some(now - Duration::days(5)));
+        running.status.as_mut().unwrap().phase = Some("Running".to_string());
+        assert_eq!(
+            predicate.evaluate(&running, now).to_string(),
+            "skipped: phase Running not in [Failed]"
+        );
+
+        let mut failed = running.clone();
+        failed.status.as_mut().unwrap().phase = Some("Failed".to_string());
+        assert!(predicate
+            .evaluate(&failed, now)
+            .to_string()
+            .starts_with("selected: "));
+    }
+
+    #[test]
+    fn excluded_namespaces_are_skipped() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let predicate = PodPredicate::from_options(&options()).unwrap();
+
+        let system = predicate.evaluate(&pod("kube-system", "a", old), now);
+        assert!(!system.matched);
+        assert_eq!(system.predicate, "system-namespace");
+
+        let pattern = predicate.evaluate(&pod("kube-extra", "b", old), now);
+        assert!(!pattern.matched);
+        assert_eq!(pattern.predicate, "namespace-pattern");
+
+        assert!(predicate.evaluate(&pod("apps", "c", old), now).matched);
+    }
+
+    #[test]
+    fn overridden_pattern_keeps_system_namespaces_protected() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        // matches no namespace, the stock pattern no longer covers kube-*
+        opts.exclude_namespace_pattern = "^$".to_string();
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        for ns in [
+            "kube-system",
+            "kube-public",
+            "kube-node-lease",
+            "openshift-monitoring",
+        ] {
+            let verdict = predicate.evaluate(&pod(ns, "a", old), now);
+            assert!(!verdict.matched, "{}", ns);
+            assert_eq!(verdict.predicate, "system-namespace", "{}", ns);
+        }
+        // only the denylist, not the old pattern
+        assert!(
+            predicate
+                .evaluate(&pod("kube-extra", "b", old), now)
+                .matched
+        );
+    }
+
+    #[test]
+    fn system_namespaces_can_be_allowed() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        opts.exclude_namespace_pattern = "^$".to_string();
+        opts.allow_system_namespaces = true;
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        assert!(
+            predicate
+                .evaluate(&pod("kube-system", "a", old), now)
+                .matched
+        );
+    }
+
+    #[test]
+    fn protection_annotation_keeps_pods_unless_false() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        opts.protection_annotation = Some("shopvac.io/protect".to_string());
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let protected = annotated(pod("apps", "a", old), "shopvac.io/protect", "true");
+        let verdict = predicate.evaluate(&protected, now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "protection-annotation");
+
+        let unprotected = annotated(pod("apps", "b", old), "shopvac.io/protect", "false");
+        assert!(predicate.evaluate(&unprotected, now).matched);
+        assert!(predicate.evaluate(&pod("apps", "c", old), now).matched);
+    }
+
+    #[test]
+    fn long_running_healthy_pods_are_kept_whatever_their_age() {
+        let now = offset::Utc::now();
+        let mut opts = options();
+        opts.exclude_running_longer_than = Some(Duration::days(30));
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let running = |name: &str, started_days: i64, ready: &str| {
+            let mut p = pod("apps", name, Some(now - Duration::days(200)));
+            let status = p.status.as_mut().unwrap();
+            status.phase = Some("Running".to_string());
+            status.start_time = Some(Time(now - Duration::days(started_days)));
+            status.conditions = Some(vec![PodCondition {
+                type_: "Ready".to_string(),
+                status: ready.to_string(),
+                ..PodCondition::default()
+            }]);
+            p
+        };
+
+        let pet = predicate.evaluate(&running("pet", 200, "True"), now);
+        assert!(!pet.matched);
+        assert_eq!(pet.predicate, "running-longer-than");
+        assert_eq!(pet.reason, "running and ready for 200d0h, > 30d0h");
+
+        // restarted recently, unhealthy or finished pods still go by age
+        assert!(predicate.evaluate(&running("new", 2, "True"), now).matched);
+        assert!(
+            predicate
+                .evaluate(&running("stuck", 200, "False"), now)
+                .matched
+        );
+        let done = pod("apps", "done", Some(now - Duration::days(200)));
+        assert!(predicate.evaluate(&done, now).matched);
+    }
+
+    #[test]
+    fn completed_for_replaces_creation_age_of_finished_pods() {
+        let now = offset::Utc::now();
+        let mut opts = options();
+        opts.completed_for = Some(Duration::hours(2));
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let just_finished = finished(
+            pod("apps", "a", Some(now - Duration::days(30))),
+            now - Duration::minutes(10),
+        );
+        let verdict = predicate.evaluate(&just_finished, now);
+        assert!(!verdict.matched);
+        assert!(
+            verdict.reason.contains("completed 10m ago"),
+            "{}",
+            verdict.reason
+        );
+
+        let young_but_done = finished(
+            pod("apps", "b", Some(now - Duration::hours(5))),
+            now - Duration::hours(4),
+        );
+        assert!(predicate.evaluate(&young_but_done, now).matched);
+
+        let no_statuses = pod("apps", "c", Some(now - Duration::days(30)));
+        assert!(!predicate.evaluate(&no_statuses, now).matched);
+
+        let mut running = pod("apps", "d", Some(now - Duration::days(30)));
+        running.status.as_mut().unwrap().phase = Some("Running".to_string());
+        assert!(predicate.evaluate(&running, now).matched);
+    }
+
+    #[test]
+    fn reason_filter_matches_status_reason() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        opts.reasons = vec!["Evicted".to_string()];
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let mut evicted = pod("apps", "a", old);
+        evicted.status.as_mut().unwrap().reason = Some("Evicted".to_string());
+        assert!(predicate.evaluate(&evicted, now).matched);
+
+        let verdict = predicate.evaluate(&pod("apps", "b", old), now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "reason");
+        assert_eq!(verdict.reason, "no status reason");
+    }
+
+    #[test]
+    fn reason_filter_ignores_the_age() {
+        let now = offset::Utc::now();
+        let mut opts = options();
+        opts.reasons = vec!["Evicted".to_string()];
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let mut evicted = pod("apps", "a", Some(now - Duration::minutes(5)));
+        evicted.status.as_mut().unwrap().reason = Some("Evicted".to_string());
+        let verdict = predicate.evaluate(&evicted, now);
+        assert!(verdict.matched);
+        assert!(!verdict.reason.contains("age"), "{}", verdict.reason);
+    }
+
+    #[test]
+    fn created_after_skips_pods_an_earlier_run_saw() {
+        let now = offset::Utc::now();
+        let mut opts = options();
+        opts.created_after = Some(now - Duration::hours(80));
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let seen = pod("apps", "seen", Some(now - Duration::hours(90)));
+        let verdict = predicate.evaluate(&seen, now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "since-last-run");
+        assert!(
+            predicate
+                .evaluate(&pod("apps", "new", Some(now - Duration::hours(75))), now)
+                .matched
+        );
+    }
+
+    #[test]
+    fn excluded_pod_names_are_skipped() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        opts.exclude_pod_pattern = Some("^keep-".to_string());
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        assert!(
+            predicate
+                .evaluate(&pod("apps", "build-1", old), now)
+                .matched
+        );
+        let verdict = predicate.evaluate(&pod("apps", "keep-me", old), now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "pod-pattern");
+    }
+
+    #[test]
+    fn owner_kind_filter_needs_a_matching_owner() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        opts.owner_kinds = vec!["Job".to_string()];
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        let owned_by = |kind: &str| {
+            let mut p = pod("apps", "a", old);
+            p.metadata.owner_references = Some(vec![OwnerReference {
+                kind: kind.to_string(),
+                ..OwnerReference::default()
+            }]);
+            p
+        };
+        assert!(predicate.evaluate(&owned_by("Job"), now).matched);
+        assert!(!predicate.evaluate(&owned_by("ReplicaSet"), now).matched);
+
+        let verdict = predicate.evaluate(&pod("apps", "b", old), now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "owner-kind");
+        assert_eq!(verdict.reason, "no owner");
+    }
+
+    #[test]
+    fn naked_only_skips_owned_pods() {
+        let now = offset::Utc::now();
+        let old = Some(now - Duration::days(30));
+        let mut opts = options();
+        opts.naked_only = true;
+        let predicate = PodPredicate::from_options(&opts).unwrap();
+
+        assert!(predicate.evaluate(&pod("apps", "bare", old), now).matched);
+
+        let mut owned = pod("apps", "owned", old);
+        owned.metadata.owner_references = Some(vec![OwnerReference {
+            kind: "ReplicaSet".to_string(),
+            ..OwnerReference::default()
+        }]);
+        let verdict = predicate.evaluate(&owned, now);
+        assert!(!verdict.matched);
+        assert_eq!(verdict.predicate, "naked-only");
+        assert_eq!(verdict.reason, "owned by [ReplicaSet]");
+    }
+}
@@ -0,0 +1,45 @@
+//! Pod scanning and deletion shared by the shopvac binaries, so the ad-hoc CLI
+//! and the CronJobs the controller schedules agree on what gets cleaned up.
+use chrono::Duration;
+
+pub mod activity;
+pub mod delete;
+pub mod dynamic;
+pub mod error;
+pub mod filter;
+pub mod owners;
+pub mod pvc;
+pub mod replicaset;
+pub mod requests;
+pub mod scan;
+#[cfg(test)]
+mod testing;
+
+pub use activity::skip_recently_active;
+pub use delete::{
+    delete_objects, delete_pods, delete_stream, DeleteOptions, DeleteReport, Outcome,
+};
+pub use dynamic::{discover, scan_dynamic, Gvk};
+pub use error::ShopvacError;
+pub use owners::retain_orphans;
+pub use pvc::{delete_pvcs, scan_pvcs};
+pub use replicaset::scan_replicasets;
+pub use requests::Requests;
+pub use scan::{
+    parse_namespace_list, scan_cluster, scan_namespace, scan_namespaces, scan_pods, scan_stream,
+    select_pods, PodTarget, ScanOptions, ScanReport,
+};
+
+/// Formats an age the way kubectl does, e.g. `3d4h`, `5h12m` or `40s`.
+pub fn format_age(age: Duration) -> String {
+    let (days, hours, minutes) = (age.num_days(), age.num_hours() % 24, age.num_minutes() % 60);
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", age.num_seconds().max(0))
+    }
+}
@@ -0,0 +1,243 @@
+//! Pods whose owners are gone, left behind when garbage collection didn't get
+//! to them, e.g. after a ReplicaSet was deleted with `--cascade=orphan`.
+use std::collections::HashMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::{
+    api::{Api, DynamicObject},
+    core::GroupVersionKind,
+    discovery::{self, ApiResource, Scope},
+    Client, ResourceExt,
+};
+
+use crate::{error::Result, scan::PodTarget};
+
+/// Whether an owner still exists, per namespace, owner kind, name and uid.
+/// The uid is part of the key since pods of an owner deleted and recreated
+/// under the same name reference either one.
+type Lookups = HashMap<(String, String, String, String, String), Option<bool>>;
+
+/// Keeps the targets whose owners no longer exist, or were replaced by a new
+/// object of the same name.
+///
+/// Pods without owners are dropped, as are pods with an owner that couldn't
+/// be looked up, only pods known to be orphaned are kept.
+pub async fn retain_orphans(client: Client, targets: Vec<PodTarget>) -> Result<Vec<PodTarget>> {
+    let mut resources: HashMap<(String, String), Option<(ApiResource, Scope)>> = HashMap::new();
+    let mut lookups = Lookups::new();
+    let mut orphans = Vec::new();
+
+    'targets: for mut target in targets {
+        let owners = target.pod.owner_references().to_vec();
+        if owners.is_empty() {
+            continue;
+        }
+        let ns = target.namespace();
+        let mut gone = Vec::new();
+        for owner in &owners {
+            let key = (
+                ns.clone(),
+                owner.api_version.clone(),
+                owner.kind.clone(),
+                owner.name.clone(),
+                owner.uid.clone(),
+            );
+            let exists = match lookups.get(&key) {
+                Some(exists) => *exists,
+                None => {
+                    let exists = owner_exists(&client, &mut resources, &ns, owner).await;
+                    lookups.insert(key, exists);
+                    exists
+                }
+            };
+            match exists {
+                Some(false) => gone.push(format!("{} {}", owner.kind, owner.name)),
+                // still around, or no way to tell
+                _ => continue 'targets,
+            }
+        }
+        target.reason = format!("{}; owner {} is gone", target.reason, gone.join(", "));
+        orphans.push(target);
+    }
+    Ok(orphans)
+}
+
+/// Looks up the owner by uid, `None` when it couldn't be checked.
+async fn owner_exists(
+    client: &Client,
+    resources: &mut HashMap<(String, String), Option<(ApiResource, Scope)>>,
+    ns: &str,
+    owner: &OwnerReference,
+) -> Option<bool> {
+    let kind = (owner.api_version.clone(), owner.kind.clone());
+    if !resources.contains_key(&kind) {
+        let (group, version) = match owner.api_version.split_once('/') {
+            Some((group, version)) => (group, version),
+            None => ("", owner.api_version.as_str()),
+        };
+        let gvk = GroupVersionKind::gvk(group, version, &owner.kind);
+        let resource = match discovery::pinned_kind(client, &gvk).await {
+            Ok((ar, caps)) => Some((ar, caps.scope)),
+            Err(e) => {
+                tracing::warn!(
+                    "Unable to discover owner kind {} {}: {}",
+                    owner.api_version,
+                    owner.kind,
+                    e
+                );
+                None
+            }
+        };
+        resources.insert(kind.clone(), resource);
+    }
+    let (ar, scope) = resources[&kind].as_ref()?;
+
+    let api: Api<DynamicObject> = match scope {
+        Scope::Namespaced => Api::namespaced_with(client.clone(), ns, ar),
+        Scope::Cluster => Api::all_with(client.clone(), ar),
+    };
+    match api.get(&owner.name).await {
+        Ok(obj) => Some(obj.uid().as_deref() == Some(owner.uid.as_str())),
+        Err(kube::Error::Api(e)) if e.code == 404 => Some(false),
+        Err(e) => {
+            tracing::warn!(
+                "Unable to look up owner {} {} in {}: {}",
+                owner.kind,
+                owner.name,
+                ns,
+                e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, Duration};
+    use http::{Request, Response};
+    use hyper::Body;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+    use serde_json::json;
+    use tokio::task::JoinHandle;
+    use tower_test::mock::Handle;
+
+    use super::retain_orphans;
+    use crate::{
+        scan::PodTarget,
+        testing::{json_response, mock_client, pod},
+    };
+
+    fn owned_by(name: &str, owner: Option<&str>) -> PodTarget {
+        owned_by_uid(name, owner.map(|owner| (owner, format!("{}-uid", owner))))
+    }
+
+    fn owned_by_uid(name: &str, owner: Option<(&str, String)>) -> PodTarget {
+        let mut p = pod("apps", name, Some(offset::Utc::now() - Duration::days(30)));
+        p.metadata.owner_references = owner.map(|(owner, uid)| {
+            vec![OwnerReference {
+                api_version: "apps/v1".to_string(),
+                kind: "ReplicaSet".to_string(),
+                name: owner.to_string(),
+                uid,
+                ..OwnerReference::default()
+            }]
+        });
+        PodTarget {
+            pod: p,
+            reason: "age 30d0h > 3d0h".to_string(),
+        }
+    }
+
+    /// Serves the ReplicaSets `alive` and `recreated`, the latter with a new
+    /// uid, any other is not found.
+    fn replica_sets(mut handle: Handle<Request<Body>, Response<Body>>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some((request, send)) = handle.next_request().await {
+                let response = match request.uri().path() {
+                    "/apis/apps/v1" => json_response(
+                        200,
+                        json!({
+                            "kind": "APIResourceList",
+                            "apiVersion": "v1",
+                            "groupVersion": "apps/v1",
+                            "resources": [{
+                                "name": "replicasets",
+                                "singularName": "replicaset",
+                                "namespaced": true,
+                                "kind": "ReplicaSet",
+                                "verbs": ["get", "list"],
+                            }],
+                        }),
+                    ),
+                    "/apis/apps/v1/namespaces/apps/replicasets/alive" => json_response(
+                        200,
+                        json!({
+                            "apiVersion": "apps/v1",
+                            "kind": "ReplicaSet",
+                            "metadata": { "name": "alive", "namespace": "apps", "uid": "alive-uid" },
+                        }),
+                    ),
+                    "/apis/apps/v1/namespaces/apps/replicasets/recreated" => json_response(
+                        200,
+                        json!({
+                            "apiVersion": "apps/v1",
+                            "kind": "ReplicaSet",
+                            "metadata": { "name": "recreated", "namespace": "apps", "uid": "new-uid" },
+                        }),
+                    ),
+                    path => json_response(
+                        404,
+                        json!({
+                            "kind": "Status",
+                            "apiVersion": "v1",
+                            "metadata": {},
+                            "status": "Failure",
+                            "message": format!("{} not found", path),
+                            "reason": "NotFound",
+                            "code": 404,
+                        }),
+                    ),
+                };
+                send.send_response(response);
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn keeps_pods_whose_owner_is_gone() {
+        let (client, handle) = mock_client();
+        let server = replica_sets(handle);
+
+        let targets = vec![
+            owned_by("a", Some("gone")),
+            owned_by("b", Some("alive")),
+            owned_by("c", Some("recreated")),
+            owned_by("d", None),
+            owned_by("e", Some("gone")),
+        ];
+        let orphans = retain_orphans(client, targets).await.unwrap();
+        server.await.unwrap();
+
+        let names: Vec<String> = orphans.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["a", "c", "e"]);
+        assert!(orphans[0].reason.ends_with("owner ReplicaSet gone is gone"));
+    }
+
+    #[tokio::test]
+    async fn owners_are_told_apart_by_uid() {
+        let (client, handle) = mock_client();
+        let server = replica_sets(handle);
+
+        // both reference `recreated`, only one the object that exists now
+        let targets = vec![
+            owned_by_uid("stale", Some(("recreated", "old-uid".to_string()))),
+            owned_by_uid("live", Some(("recreated", "new-uid".to_string()))),
+        ];
+        let orphans = retain_orphans(client, targets).await.unwrap();
+        server.await.unwrap();
+
+        let names: Vec<String> = orphans.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["stale"]);
+    }
+}
@@ -0,0 +1,82 @@
+//! PersistentVolumeClaims left behind once their pods are gone, typically from
+//! StatefulSets that were deleted without their volume claim templates.
+use std::collections::HashSet;
+
+use chrono::offset;
+use futures::future::Future;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Pod};
+use kube::{
+    api::{Api, ListParams},
+    Client, ResourceExt,
+};
+use regex::Regex;
+
+use crate::{
+    delete::{delete_each, DeleteOptions, DeleteReport},
+    error::Result,
+    filter::object_selected,
+    scan::ScanOptions,
+};
+
+/// Lists the claims visible through `pvcs` and returns the ones matching
+/// `options` that no running pod visible through `pods` still mounts.
+///
+/// Only the namespace, annotation and age options apply, the pod specific
+/// ones (phases, image pattern) are ignored.
+pub async fn scan_pvcs(
+    pvcs: &Api<PersistentVolumeClaim>,
+    pods: &Api<Pod>,
+    options: &ScanOptions,
+) -> Result<Vec<PersistentVolumeClaim>> {
+    let ns_regex = Regex::new(&options.exclude_namespace_pattern)?;
+
+    let claims = pvcs.list(&options.list_params()).await?;
+
+    // every claim still referenced by a pod that hasn't finished, the
+    // selectors above are for the claims so the pods are listed unfiltered
+    let in_use: HashSet<(String, String)> = pods
+        .list(&ListParams::default())
+        .await?
+        .iter()
+        .filter(|p| {
+            let phase = p.status.as_ref().and_then(|s| s.phase.as_deref());
+            !matches!(phase, Some("Succeeded") | Some("Failed"))
+        })
+        .flat_map(|p| {
+            let ns = p.namespace().unwrap_or_default();
+            p.spec
+                .iter()
+                .flat_map(|s| s.volumes.iter().flatten())
+                .filter_map(|v| v.persistent_volume_claim.as_ref())
+                .map(move |c| (ns.clone(), c.claim_name.clone()))
+        })
+        .collect();
+
+    let now = offset::Utc::now();
+    Ok(claims
+        .into_iter()
+        .filter(|c| object_selected(c, options, &ns_regex, now))
+        .filter(|c| {
+            let key = (c.namespace().unwrap_or_default(), c.name());
+            if in_use.contains(&key) {
+                tracing::debug!(
+                    "Skipping claim {}:{}, still mounted by a running pod",
+                    key.0,
+                    key.1
+                );
+                return false;
+            }
+            true
+        })
+        .collect())
+}
+
+/// Deletes `claims` the same way [`crate::delete_pods`] deletes pods.
+pub async fn delete_pvcs(
+    client: Client,
+    claims: &[PersistentVolumeClaim],
+    options: DeleteOptions,
+    shutdown: impl Future<Output = ()>,
+) -> DeleteReport<'_, PersistentVolumeClaim> {
+    delete_each(client, claims, |c| c, (), options, shutdown).await
+}
@@ -0,0 +1,157 @@
+//! ReplicaSets scaled to zero, which Deployments leave behind after every
+//! rollout up to their `revisionHistoryLimit`.
+use std::collections::HashSet;
+
+use chrono::offset;
+use k8s_openapi::api::{apps::v1::ReplicaSet, core::v1::Pod};
+use kube::{
+    api::{Api, ListParams},
+    ResourceExt,
+};
+use regex::Regex;
+
+use crate::{error::Result, filter::object_selected, scan::ScanOptions};
+
+/// Lists the ReplicaSets visible through `replicasets` and returns the ones
+/// matching `options` that are scaled to zero, both in their spec and their
+/// status, and own no pod visible through `pods` that hasn't finished.
+///
+/// Only the namespace, annotation and age options apply. Deleting the old
+/// ReplicaSets of a Deployment drops the revisions `kubectl rollout undo`
+/// could go back to.
+pub async fn scan_replicasets(
+    replicasets: &Api<ReplicaSet>,
+    pods: &Api<Pod>,
+    options: &ScanOptions,
+) -> Result<Vec<ReplicaSet>> {
+    let ns_regex = Regex::new(&options.exclude_namespace_pattern)?;
+
+    let listed = replicasets.list(&options.list_params()).await?;
+
+    // the uids of every ReplicaSet with a pod that is still running, or about
+    // to, in case the status is behind
+    let live_owners: HashSet<String> = pods
+        .list(&ListParams::default())
+        .await?
+        .iter()
+        .filter(|p| {
+            let phase = p.status.as_ref().and_then(|s| s.phase.as_deref());
+            !matches!(phase, Some("Succeeded") | Some("Failed"))
+        })
+        .flat_map(|p| p.owner_references().to_vec())
+        .filter(|o| o.kind == "ReplicaSet")
+        .map(|o| o.uid)
+        .collect();
+
+    let now = offset::Utc::now();
+    Ok(listed
+        .into_iter()
+        .filter(|rs| object_selected(rs, options, &ns_regex, now))
+        .filter(scaled_to_zero)
+        .filter(|rs| {
+            if rs.uid().map_or(false, |uid| live_owners.contains(&uid)) {
+                tracing::debug!(
+                    "Skipping ReplicaSet {}:{}, it still has live pods",
+                    rs.namespace().unwrap_or_default(),
+                    rs.name()
+                );
+                return false;
+            }
+            true
+        })
+        .collect())
+}
+
+/// Whether both the desired and the observed replicas are zero.
+fn scaled_to_zero(rs: &ReplicaSet) -> bool {
+    let desired = rs.spec.as_ref().and_then(|s| s.replicas);
+    let observed = rs.status.as_ref().map(|s| s.replicas);
+    desired == Some(0) && observed == Some(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, Duration};
+    use k8s_openapi::api::{apps::v1::ReplicaSet, core::v1::Pod};
+    use kube::api::Api;
+    use serde_json::json;
+
+    use super::scan_replicasets;
+    use crate::testing::{json_response, mock_client, options};
+
+    #[tokio::test]
+    async fn only_empty_replicasets_without_live_pods() {
+        let (client, mut handle) = mock_client();
+        let created = (offset::Utc::now() - Duration::days(30)).to_rfc3339();
+
+        let server = tokio::spawn(async move {
+            while let Some((request, send)) = handle.next_request().await {
+                let rs = |name: &str, desired: i32, observed: i32| {
+                    json!({
+                        "metadata": {
+                            "name": name,
+                            "namespace": "apps",
+                            "uid": format!("{}-uid", name),
+                            "creationTimestamp": created,
+                        },
+                        "spec": { "replicas": desired, "selector": {} },
+                        "status": { "replicas": observed },
+                    })
+                };
+                let response = match request.uri().path() {
+                    "/apis/apps/v1/namespaces/apps/replicasets" => json_response(
+                        200,
+                        json!({
+                            "apiVersion": "apps/v1",
+                            "kind": "ReplicaSetList",
+                            "metadata": {},
+                            "items": [
+                                rs("web-old", 0, 0),
+                                rs("web-new", 3, 3),
+                                rs("web-scaling", 0, 1),
+                                rs("web-stale", 0, 0),
+                            ],
+                        }),
+                    ),
+                    "/api/v1/namespaces/apps/pods" => json_response(
+                        200,
+                        json!({
+                            "apiVersion": "v1",
+                            "kind": "PodList",
+                            "metadata": {},
+                            "items": [{
+                                "metadata": {
+                                    "name": "web-stale-x",
+                                    "namespace": "apps",
+                                    "ownerReferences": [{
+                                        "apiVersion": "apps/v1",
+                                        "kind": "ReplicaSet",
+                                        "name": "web-stale",
+                                        "uid": "web-stale-uid",
+                                    }],
+                                },
+                                "status": { "phase": "Running" },
+                            }],
+                        }),
+                    ),
+                    path => panic!("unexpected request to {}", path),
+                };
+                send.send_response(response);
+            }
+        });
+
+        let replicasets: Api<ReplicaSet> = Api::namespaced(client.clone(), "apps");
+        let pods: Api<Pod> = Api::namespaced(client, "apps");
+        let found = scan_replicasets(&replicasets, &pods, &options())
+            .await
+            .unwrap();
+        drop((replicasets, pods));
+        server.await.unwrap();
+
+        let names: Vec<String> = found
+            .iter()
+            .map(|rs| rs.metadata.name.clone().unwrap())
+            .collect();
+        assert_eq!(names, vec!["web-old"]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Totalling the resource requests of pods, to show what a cleanup frees up.
+use std::{fmt, iter::Sum, ops::Add};
+
+use k8s_openapi::{api::core::v1::Pod, apimachinery::pkg::api::resource::Quantity};
+
+/// CPU and memory requested by a set of pods.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Requests {
+    pub cpu_cores: f64,
+    pub memory_bytes: f64,
+}
+
+impl Requests {
+    /// The requests of the pod's regular containers, init containers have
+    /// already finished by the time a pod is worth cleaning up. Missing or
+    /// unparseable requests count as zero.
+    pub fn of(pod: &Pod) -> Self {
+        pod.spec
+            .iter()
+            .flat_map(|s| &s.containers)
+            .filter_map(|c| c.resources.as_ref()?.requests.as_ref())
+            .map(|requests| Requests {
+                cpu_cores: requests.get("cpu").and_then(parse_quantity).unwrap_or(0.0),
+                memory_bytes: requests
+                    .get("memory")
+                    .and_then(parse_quantity)
+                    .unwrap_or(0.0),
+            })
+            .sum()
+    }
+}
+
+impl Add for Requests {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Requests {
+            cpu_cores: self.cpu_cores + other.cpu_cores,
+            memory_bytes: self.memory_bytes + other.memory_bytes,
+        }
+    }
+}
+
+impl Sum for Requests {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Requests::default(), Add::add)
+    }
+}
+
+impl fmt::Display for Requests {
+    /// e.g. `12 cores / 48Gi`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: &[&str] = &["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+        let mut memory = self.memory_bytes;
+        let mut unit = 0;
+        while memory >= 1024.0 && unit < UNITS.len() - 1 {
+            memory /= 1024.0;
+            unit += 1;
+        }
+        write!(
+            f,
+            "{} cores / {}{}",
+            trim(format!("{:.2}", self.cpu_cores)),
+            trim(format!("{:.1}", memory)),
+            UNITS[unit]
+        )
+    }
+}
+
+fn trim(n: String) -> String {
+    n.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Parses a Kubernetes quantity such as `250m`, `1.5`, `128Mi` or `1e3` into
+/// its plain value.
+pub fn parse_quantity(quantity: &Quantity) -> Option<f64> {
+    let q = quantity.0.trim();
+    let split = q
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+' || c == '-'))
+        .unwrap_or(q.len());
+    let (number, suffix) = q.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024f64,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        "Ei" => 1024f64.powi(6),
+        exp if exp.starts_with('e') || exp.starts_with('E') => 10f64.powi(exp[1..].parse().ok()?),
+        _ => return None,
+    };
+    Some(number * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::{
+        api::core::v1::{Container, PodSpec, ResourceRequirements},
+        apimachinery::pkg::api::resource::Quantity,
+    };
+
+    use super::{parse_quantity, Requests};
+    use crate::testing::pod;
+
+    fn quantity(q: &str) -> Option<f64> {
+        parse_quantity(&Quantity(q.to_string()))
+    }
+
+    #[test]
+    fn parses_quantities() {
+        assert_eq!(quantity("250m"), Some(0.25));
+        assert_eq!(quantity("2"), Some(2.0));
+        assert_eq!(quantity("1.5"), Some(1.5));
+        assert_eq!(quantity("128Mi"), Some(128.0 * 1024.0 * 1024.0));
+        assert_eq!(quantity("1G"), Some(1e9));
+        assert_eq!(quantity("1e3"), Some(1000.0));
+        assert_eq!(quantity("lots"), None);
+    }
+
+    #[test]
+    fn sums_container_requests() {
+        let container = |cpu: &str, memory: &str| Container {
+            name: "main".to_string(),
+            resources: Some(ResourceRequirements {
+                requests: Some(BTreeMap::from([
+                    ("cpu".to_string(), Quantity(cpu.to_string())),
+                    ("memory".to_string(), Quantity(memory.to_string())),
+                ])),
+                ..ResourceRequirements::default()
+            }),
+            ..Container::default()
+        };
+        let mut with_requests = pod("apps", "a", None);
+        with_requests.spec = Some(PodSpec {
+            containers: vec![container("500m", "1Gi"), container("1500m", "512Mi")],
+            ..PodSpec::default()
+        });
+        let without_requests = pod("apps", "b", None);
+
+        let total: Requests = [&with_requests, &without_requests]
+            .into_iter()
+            .map(Requests::of)
+            .sum();
+        assert_eq!(total.to_string(), "2 cores / 1.5Gi");
+    }
+}
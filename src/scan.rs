@@ -0,0 +1,651 @@
+//! Listing pods and narrowing them down to the ones that should be cleaned up.
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{offset, DateTime, Duration, Utc};
+use futures::stream::{self, Stream, TryStreamExt};
+use k8s_openapi::{
+    api::core::v1::{Namespace, Pod},
+    http::header::{HeaderValue, ACCEPT},
+};
+use kube::{
+    api::{Api, ListParams, ObjectList, ObjectMeta, ResourceExt},
+    core::Request,
+    Client,
+};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    error::{Result, ShopvacError},
+    filter::{is_system_namespace, AnnotationSelector, PodPhase, PodPredicate},
+};
+
+/// What to look for. The selectors are handed to the apiserver, everything
+/// else is evaluated client side through a [`PodPredicate`].
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    pub label_selector: Option<String>,
+    /// Labels pods must not carry, `key=value` or just `key`, added to the
+    /// label selector as `key!=value` and `!key`
+    pub not_labels: Vec<String>,
+    pub field_selector: Option<String>,
+    pub older_than: Duration,
+    /// Only pods created after this, the ones an earlier run couldn't have
+    /// seen at their current age yet
+    pub created_after: Option<DateTime<Utc>>,
+    /// For pods in a terminal phase, the time since their last container
+    /// finished that replaces the creation age check
+    pub completed_for: Option<Duration>,
+    pub exclude_namespace_pattern: String,
+    pub allow_system_namespaces: bool,
+    pub annotation_selectors: Vec<AnnotationSelector>,
+    pub phases: Vec<PodPhase>,
+    /// `status.reason` values to match, e.g. `Evicted`. Pods with one of
+    /// them are selected whatever their age
+    pub reasons: Vec<String>,
+    pub image_pattern: Option<String>,
+    /// Pods whose name matches are never selected
+    pub exclude_pod_pattern: Option<String>,
+    /// Kinds of owner, e.g. `Job`, at least one of which the pod must have
+    pub owner_kinds: Vec<String>,
+    /// Only pods without any owner reference, the ones nothing would
+    /// recreate or garbage collect
+    pub naked_only: bool,
+    pub protection_annotation: Option<String>,
+    /// Pods that are Running and Ready and started longer ago than this are
+    /// never selected, whatever their age
+    pub exclude_running_longer_than: Option<Duration>,
+    /// Pods fetched per list call
+    pub chunk_size: u32,
+    /// Log why every listed pod was selected or skipped, not just the
+    /// matches
+    pub explain: bool,
+}
+
+/// A pod selected for cleanup.
+#[derive(Clone, Debug)]
+pub struct PodTarget {
+    /// Only `metadata` is set when the scan listed metadata only, see
+    /// [`ScanOptions::metadata_only`]
+    pub pod: Pod,
+    /// Why the predicate selected it
+    pub reason: String,
+}
+
+impl PodTarget {
+    pub fn namespace(&self) -> String {
+        self.pod.namespace().unwrap_or_default()
+    }
+
+    pub fn name(&self) -> String {
+        self.pod.name()
+    }
+}
+
+impl ScanOptions {
+    /// The part of the options the apiserver evaluates, for list and watch
+    /// calls alike.
+    pub fn list_params(&self) -> ListParams {
+        let mut lp = ListParams::default();
+        let selectors: Vec<String> = self
+            .label_selector
+            .iter()
+            .cloned()
+            .chain(self.not_labels.iter().map(|l| match l.split_once('=') {
+                Some((key, value)) => format!("{}!={}", key, value),
+                None => format!("!{}", l),
+            }))
+            .collect();
+        if !selectors.is_empty() {
+            lp = lp.labels(&selectors.join(","))
+        }
+        if let Some(fs) = &self.field_selector {
+            lp = lp.fields(fs)
+        }
+        lp
+    }
+
+    /// True when no option looks past the pod metadata, so [`scan_pods`]
+    /// skips fetching specs and statuses altogether.
+    pub fn metadata_only(&self) -> bool {
+        PodPredicate::from_options(self).map_or(false, |p| !p.needs_full_object())
+    }
+}
+
+/// An item of a `PartialObjectMetadataList`.
+#[derive(Clone, Debug, Deserialize)]
+struct PartialObjectMetadata {
+    metadata: ObjectMeta,
+}
+
+/// Fetches one page of pods, with only their metadata when `metadata_only`
+/// is set, which on big clusters is a fraction of the memory full pod
+/// objects take. Returns the page and the token for the next one.
+async fn list_page(
+    api: &Api<Pod>,
+    lp: &ListParams,
+    metadata_only: bool,
+) -> kube::Result<(Vec<Pod>, Option<String>)> {
+    if !metadata_only {
+        let list = api.list(lp).await?;
+        return Ok((list.items, list.metadata.continue_));
+    }
+
+    let mut request = Request::new(api.resource_url())
+        .list(lp)
+        .map_err(kube::Error::BuildRequest)?;
+    request.headers_mut().insert(
+        ACCEPT,
+        HeaderValue::from_static(
+            "application/json;as=PartialObjectMetadataList;g=meta.k8s.io;v=v1",
+        ),
+    );
+    let list: ObjectList<PartialObjectMetadata> =
+        api.clone().into_client().request(request).await?;
+    let pods = list
+        .items
+        .into_iter()
+        .map(|p| Pod {
+            metadata: p.metadata,
+            ..Pod::default()
+        })
+        .collect();
+    Ok((pods, list.metadata.continue_))
+}
+
+/// Lists the pods visible through `api` and returns the ones matching
+/// `options`.
+///
+/// Pods are listed `options.chunk_size` at a time and filtered page by page,
+/// so only the matches are held on to. An expired continue token restarts
+/// the scan from the first page.
+pub async fn scan_pods(api: &Api<Pod>, options: &ScanOptions) -> Result<Vec<PodTarget>> {
+    Ok(scan_namespace(api, options).await?.targets)
+}
+
+/// [`scan_pods`], also counting the pods that were looked at.
+pub async fn scan_namespace(api: &Api<Pod>, options: &ScanOptions) -> Result<ScanReport> {
+    let mut pager = Pager::new(api.clone(), options)?;
+    let mut targets = Vec::new();
+    while let Some(page) = pager.next_page().await? {
+        targets.extend(page);
+    }
+    Ok(ScanReport {
+        targets,
+        scanned: pager.listed,
+        forbidden: Vec::new(),
+    })
+}
+
+/// Like [`scan_pods`], but yields the matches of each page as soon as it is
+/// listed, so they can be deleted while later pages are still being fetched.
+pub fn scan_stream(
+    api: Api<Pod>,
+    options: &ScanOptions,
+) -> Result<impl Stream<Item = Result<PodTarget>>> {
+    let pager = Pager::new(api, options)?;
+    Ok(stream::try_unfold(pager, |mut pager| async move {
+        Ok::<_, ShopvacError>(pager.next_page().await?.map(|page| (page, pager)))
+    })
+    .map_ok(|page| stream::iter(page.into_iter().map(Ok::<_, ShopvacError>)))
+    .try_flatten())
+}
+
+/// Walks a paginated pod list, filtering each page as it arrives.
+struct Pager {
+    api: Api<Pod>,
+    lp: ListParams,
+    metadata_only: bool,
+    selection: Selection,
+    /// Every match handed out so far, so a restarted list doesn't repeat them
+    seen: HashSet<(String, String)>,
+    listed: usize,
+    done: bool,
+}
+
+impl Pager {
+    fn new(api: Api<Pod>, options: &ScanOptions) -> Result<Self> {
+        let metadata_only = options.metadata_only();
+        if metadata_only {
+            tracing::debug!("No filter needs pod specs or statuses, listing metadata only");
+        }
+        let mut lp = options.list_params();
+        lp.limit = Some(options.chunk_size);
+        Ok(Self {
+            api,
+            lp,
+            metadata_only,
+            selection: Selection::new(options)?,
+            seen: HashSet::new(),
+            listed: 0,
+            done: false,
+        })
+    }
+
+    /// The new matches on the next page, `None` once the list is exhausted.
+    async fn next_page(&mut self) -> Result<Option<Vec<PodTarget>>> {
+        while !self.done {
+            let (pods, next) = match list_page(&self.api, &self.lp, self.metadata_only).await {
+                Ok(page) => page,
+                Err(kube::Error::Api(e)) if e.code == 410 && self.lp.continue_token.is_some() => {
+                    tracing::warn!(
+                        "Pod list expired after {} pods, restarting the scan from the first page",
+                        self.listed
+                    );
+                    self.lp.continue_token = None;
+                    self.selection.protected.clear();
+                    self.listed = 0;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            self.listed += pods.len();
+            tracing::debug!("Listed {} pods so far", self.listed);
+            match next.filter(|token| !token.is_empty()) {
+                Some(token) => self.lp.continue_token = Some(token),
+                None => self.done = true,
+            }
+
+            self.selection.add(pods);
+            let seen = &mut self.seen;
+            let page = self
+                .selection
+                .targets
+                .drain(..)
+                .filter(|t| seen.insert((t.namespace(), t.name())))
+                .collect();
+            if self.done {
+                self.selection.log_protected();
+            }
+            return Ok(Some(page));
+        }
+        Ok(None)
+    }
+}
+
+/// The result of [`scan_namespace`], [`scan_namespaces`] or [`scan_cluster`].
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub targets: Vec<PodTarget>,
+    /// Pods listed, matching or not
+    pub scanned: usize,
+    /// Namespaces whose pods we aren't allowed to list, and were skipped
+    pub forbidden: Vec<String>,
+}
+
+/// Scans every namespace. Without permission to list pods cluster wide, the
+/// namespaces are scanned one by one and those that answer 403 are skipped
+/// instead of failing the whole scan.
+pub async fn scan_cluster(client: Client, options: &ScanOptions) -> Result<ScanReport> {
+    match scan_namespace(&Api::all(client.clone()), options).await {
+        Ok(scan) => return Ok(scan),
+        Err(e) if is_forbidden(&e) => {
+            tracing::warn!("Not allowed to list pods cluster wide, scanning namespace by namespace")
+        }
+        Err(e) => return Err(e),
+    }
+
+    // namespaces the predicate rejects anyway aren't worth a 403 each
+    let excluded = Regex::new(&options.exclude_namespace_pattern)?;
+    let namespaces: Vec<String> = Api::<Namespace>::all(client.clone())
+        .list(&ListParams::default())
+        .await?
+        .iter()
+        .map(ResourceExt::name)
+        .filter(|ns| {
+            !excluded.is_match(ns) && (options.allow_system_namespaces || !is_system_namespace(ns))
+        })
+        .collect();
+    scan_namespaces(client, &namespaces, options).await
+}
+
+/// Scans the given namespaces one by one, skipping those that answer 403.
+pub async fn scan_namespaces(
+    client: Client,
+    namespaces: &[String],
+    options: &ScanOptions,
+) -> Result<ScanReport> {
+    let mut scan = ScanReport::default();
+    for ns in namespaces {
+        match scan_namespace(&Api::namespaced(client.clone(), ns), options).await {
+            Ok(found) => {
+                scan.targets.extend(found.targets);
+                scan.scanned += found.scanned;
+            }
+            Err(e) if is_forbidden(&e) => {
+                tracing::warn!("Not allowed to list pods in namespace {}, skipping it", ns);
+                scan.forbidden.push(ns.clone());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(scan)
+}
+
+/// Parses a newline separated namespace list, ignoring blank lines, `#`
+/// comments and repeats.
+pub fn parse_namespace_list(list: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    list.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|ns| !ns.is_empty() && seen.insert(*ns))
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn is_forbidden(e: &ShopvacError) -> bool {
+    matches!(e, ShopvacError::NoPermission(_))
+}
+
+/// Returns the pods matching the client side part of `options`, for pods that
+/// were already fetched with [`ScanOptions::list_params`], e.g. from a cache.
+pub fn select_pods(
+    pods: impl IntoIterator<Item = Pod>,
+    options: &ScanOptions,
+) -> Result<Vec<PodTarget>> {
+    let mut selection = Selection::new(options)?;
+    selection.add(pods);
+    Ok(selection.finish())
+}
+
+/// The matches so far of a scan that sees its pods in batches.
+struct Selection {
+    predicate: PodPredicate,
+    explain: bool,
+    now: DateTime<Utc>,
+    /// Pods skipped per system namespace
+    protected: BTreeMap<String, usize>,
+    targets: Vec<PodTarget>,
+}
+
+impl Selection {
+    fn new(options: &ScanOptions) -> Result<Self> {
+        Ok(Self {
+            predicate: PodPredicate::from_options(options)?,
+            explain: options.explain,
+            now: offset::Utc::now(),
+            protected: BTreeMap::new(),
+            targets: Vec::new(),
+        })
+    }
+
+    fn add(&mut self, pods: impl IntoIterator<Item = Pod>) {
+        for pod in pods {
+            let verdict = self.predicate.evaluate(&pod, self.now);
+            if self.explain {
+                tracing::info!(
+                    namespace = %pod.namespace().unwrap_or_default(),
+                    pod = %pod.name(),
+                    predicate = verdict.predicate,
+                    "{}",
+                    verdict
+                );
+            }
+            if verdict.matched {
+                self.targets.push(PodTarget {
+                    pod,
+                    reason: verdict.reason,
+                });
+                continue;
+            }
+            if verdict.predicate == "system-namespace" {
+                *self
+                    .protected
+                    .entry(pod.namespace().unwrap_or_default())
+                    .or_default() += 1;
+            }
+            tracing::debug!(
+                "Skipping {}:{}, {}",
+                pod.namespace().unwrap_or_default(),
+                pod.name(),
+                verdict.reason
+            );
+        }
+    }
+
+    fn log_protected(&self) {
+        for (ns, count) in &self.protected {
+            tracing::info!(
+                "Ignored {} pods in protected system namespace {}, pass --allow-system-namespaces to include them.",
+                count,
+                ns
+            );
+        }
+    }
+
+    fn finish(self) -> Vec<PodTarget> {
+        self.log_protected();
+        self.targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{offset, Duration};
+    use k8s_openapi::api::core::v1::Pod;
+    use kube::api::Api;
+    use serde_json::json;
+
+    use super::{parse_namespace_list, scan_cluster, scan_pods};
+    use crate::{
+        filter::PodPhase,
+        testing::{json_response, mock_client, options, pod},
+    };
+
+    #[test]
+    fn not_labels_are_negated_into_the_label_selector() {
+        let mut options = options();
+        options.label_selector = Some("app=batch".to_string());
+        options.not_labels = vec!["keep=true".to_string(), "pinned".to_string()];
+        assert_eq!(
+            options.list_params().label_selector.as_deref(),
+            Some("app=batch,keep!=true,!pinned")
+        );
+    }
+
+    #[test]
+    fn namespace_lists_skip_blanks_and_comments() {
+        let list = "# generated by the inventory\napps\n\n  batch  \nci # nightly only\napps\n";
+        assert_eq!(parse_namespace_list(list), vec!["apps", "batch", "ci"]);
+    }
+
+    #[tokio::test]
+    async fn cluster_scan_keeps_namespace_and_name_together() {
+        let (client, mut handle) = mock_client();
+        let old = Some(offset::Utc::now() - Duration::days(30));
+        let new = Some(offset::Utc::now() - Duration::hours(1));
+
+        let server = tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.expect("list not sent");
+            assert_eq!(request.uri().path(), "/api/v1/pods");
+            assert!(request.headers()["accept"]
+                .to_str()
+                .unwrap()
+                .contains("PartialObjectMetadataList"));
+            send.send_response(json_response(
+                200,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "PodList",
+                    "metadata": {},
+                    "items": [
+                        pod("team-a", "build", old),
+                        pod("team-b", "build", old),
+                        pod("team-b", "fresh", new),
+                        pod("kube-system", "dns", old),
+                        pod("openshift-monitoring", "prom", old),
+                    ],
+                }),
+            ));
+        });
+
+        let api: Api<Pod> = Api::all(client);
+        let targets = scan_pods(&api, &options()).await.unwrap();
+        server.await.unwrap();
+
+        let found: Vec<(String, String)> =
+            targets.iter().map(|t| (t.namespace(), t.name())).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("team-a".to_string(), "build".to_string()),
+                ("team-b".to_string(), "build".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn phase_filter_lists_full_pods() {
+        let (client, mut handle) = mock_client();
+        let old = Some(offset::Utc::now() - Duration::days(30));
+
+        let server = tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.expect("list not sent");
+            assert!(request.headers().get("accept").map_or(true, |a| !a
+                .to_str()
+                .unwrap()
+                .contains("PartialObjectMetadataList")));
+            send.send_response(json_response(
+                200,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "PodList",
+                    "metadata": {},
+                    "items": [pod("team-a", "build", old)],
+                }),
+            ));
+        });
+
+        let mut options = options();
+        options.phases = vec![PodPhase::Succeeded];
+        let api: Api<Pod> = Api::all(client);
+        let targets = scan_pods(&api, &options).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!(targets.len(), 1);
+        assert!(targets[0].pod.status.is_some());
+    }
+
+    #[tokio::test]
+    async fn pages_through_and_restarts_on_expired_token() {
+        let (client, mut handle) = mock_client();
+        let old = Some(offset::Utc::now() - Duration::days(30));
+        let page = |name: &str, next: Option<&str>| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "PodList",
+                "metadata": { "continue": next },
+                "items": [pod("apps", name, old)],
+            })
+        };
+
+        let server = tokio::spawn(async move {
+            let mut queries = Vec::new();
+            let responses = vec![
+                json_response(200, page("a", Some("t1"))),
+                json_response(
+                    410,
+                    json!({
+                        "apiVersion": "v1",
+                        "kind": "Status",
+                        "metadata": {},
+                        "status": "Failure",
+                        "message": "continue token expired",
+                        "reason": "Expired",
+                        "code": 410,
+                    }),
+                ),
+                json_response(200, page("a", Some("t2"))),
+                json_response(200, page("b", None)),
+            ];
+            for response in responses {
+                let (request, send) = handle.next_request().await.expect("list not sent");
+                queries.push(request.uri().query().unwrap_or_default().to_string());
+                send.send_response(response);
+            }
+            queries
+        });
+
+        let mut options = options();
+        options.chunk_size = 1;
+        let api: Api<Pod> = Api::namespaced(client, "apps");
+        let targets = scan_pods(&api, &options).await.unwrap();
+        let queries = server.await.unwrap();
+
+        assert!(queries.iter().all(|q| q.contains("limit=1")));
+        assert!(!queries[0].contains("continue="));
+        assert!(queries[1].contains("continue=t1"));
+        assert!(!queries[2].contains("continue="));
+        assert!(queries[3].contains("continue=t2"));
+        let names: Vec<String> = targets.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn forbidden_namespaces_are_skipped() {
+        let (client, mut handle) = mock_client();
+        let old = Some(offset::Utc::now() - Duration::days(30));
+        let forbidden = || {
+            json_response(
+                403,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "Status",
+                    "metadata": {},
+                    "status": "Failure",
+                    "message": "pods is forbidden",
+                    "reason": "Forbidden",
+                    "code": 403,
+                }),
+            )
+        };
+
+        let server = tokio::spawn(async move {
+            let responses = vec![
+                ("/api/v1/pods", forbidden()),
+                (
+                    "/api/v1/namespaces",
+                    json_response(
+                        200,
+                        json!({
+                            "apiVersion": "v1",
+                            "kind": "NamespaceList",
+                            "metadata": {},
+                            "items": [
+                                { "metadata": { "name": "kube-system" } },
+                                { "metadata": { "name": "locked" } },
+                                { "metadata": { "name": "open" } },
+                            ],
+                        }),
+                    ),
+                ),
+                ("/api/v1/namespaces/locked/pods", forbidden()),
+                (
+                    "/api/v1/namespaces/open/pods",
+                    json_response(
+                        200,
+                        json!({
+                            "apiVersion": "v1",
+                            "kind": "PodList",
+                            "metadata": {},
+                            "items": [pod("open", "build", old)],
+                        }),
+                    ),
+                ),
+            ];
+            for (path, response) in responses {
+                let (request, send) = handle.next_request().await.expect("request not sent");
+                assert_eq!(request.uri().path(), path);
+                send.send_response(response);
+            }
+        });
+
+        let scan = scan_cluster(client, &options()).await.unwrap();
+        server.await.unwrap();
+
+        let found: Vec<String> = scan.targets.iter().map(|t| t.name()).collect();
+        assert_eq!(found, vec!["build"]);
+        assert_eq!(scan.forbidden, vec!["locked"]);
+        assert_eq!(scan.scanned, 1);
+    }
+}
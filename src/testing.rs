@@ -0,0 +1,77 @@
+//! Fixtures shared by the unit tests.
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use http::{Request, Response};
+use hyper::Body;
+use k8s_openapi::{
+    api::core::v1::{Pod, PodStatus},
+    apimachinery::pkg::apis::meta::v1::Time,
+};
+use kube::{api::ObjectMeta, Client};
+use tower_test::mock::{self, Handle};
+
+use crate::ScanOptions;
+
+/// A finished pod, created at `created` if given.
+pub fn pod(namespace: &str, name: &str, created: Option<DateTime<Utc>>) -> Pod {
+    Pod {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(namespace.to_string()),
+            creation_timestamp: created.map(Time),
+            ..ObjectMeta::default()
+        },
+        status: Some(PodStatus {
+            phase: Some("Succeeded".to_string()),
+            ..PodStatus::default()
+        }),
+        ..Pod::default()
+    }
+}
+
+pub fn annotated(mut pod: Pod, key: &str, value: &str) -> Pod {
+    pod.metadata
+        .annotations
+        .get_or_insert_with(BTreeMap::new)
+        .insert(key.to_string(), value.to_string());
+    pod
+}
+
+/// The CLI defaults: the stock exclude pattern and a 72 hour age.
+pub fn options() -> ScanOptions {
+    ScanOptions {
+        label_selector: None,
+        not_labels: Vec::new(),
+        field_selector: None,
+        older_than: Duration::hours(72),
+        created_after: None,
+        completed_for: None,
+        exclude_namespace_pattern: "(openshift.*)|(kube.*)".to_string(),
+        allow_system_namespaces: false,
+        annotation_selectors: Vec::new(),
+        phases: Vec::new(),
+        reasons: Vec::new(),
+        image_pattern: None,
+        exclude_pod_pattern: None,
+        owner_kinds: Vec::new(),
+        naked_only: false,
+        protection_annotation: None,
+        exclude_running_longer_than: None,
+        chunk_size: 500,
+        explain: false,
+    }
+}
+
+/// A client whose requests are answered through the returned handle.
+pub fn mock_client() -> (Client, Handle<Request<Body>, Response<Body>>) {
+    let (service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+    (Client::new(service, "default"), handle)
+}
+
+pub fn json_response(status: u16, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
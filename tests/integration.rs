@@ -0,0 +1,177 @@
+//! End to end checks against a real cluster, e.g. kind or k3d.
+//!
+//! Run with `cargo test --features integration`, the current `KUBECONFIG`
+//! context is used and the tests pass without doing anything when no cluster
+//! is reachable.
+#![cfg(feature = "integration")]
+
+use chrono::Duration;
+use k8s_openapi::api::core::v1::{Namespace, Pod};
+use kube::{
+    api::{Api, DeleteParams, ListParams, PostParams},
+    Client, ResourceExt,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use shopvac::{filter::PodPhase, DeleteOptions, ScanOptions};
+use tokio::time;
+
+/// A namespace that is deleted again when dropped, including on panic.
+struct Scratch {
+    client: Client,
+    name: String,
+}
+
+impl Scratch {
+    async fn create(client: Client) -> Self {
+        let suffix: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(6)
+            .map(|c| (c as char).to_ascii_lowercase())
+            .collect();
+        let name = format!("shopvac-it-{}", suffix);
+        let ns: Namespace = serde_json::from_value(json!({
+            "metadata": { "name": name }
+        }))
+        .unwrap();
+        Api::<Namespace>::all(client.clone())
+            .create(&PostParams::default(), &ns)
+            .await
+            .expect("Unable to create scratch namespace");
+        Self { client, name }
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let api = Api::<Namespace>::all(self.client.clone());
+        let name = self.name.clone();
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async move {
+                if let Err(e) = api.delete(&name, &DeleteParams::default()).await {
+                    eprintln!("Unable to delete scratch namespace {}: {}", name, e);
+                }
+            })
+        });
+    }
+}
+
+/// A client for the current context, or `None` when there's no cluster to
+/// talk to.
+async fn cluster() -> Option<Client> {
+    let client = match Client::try_default().await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Skipping, no kube config: {}", e);
+            return None;
+        }
+    };
+    match client.apiserver_version().await {
+        Ok(_) => Some(client),
+        Err(e) => {
+            eprintln!("Skipping, cluster unreachable: {}", e);
+            None
+        }
+    }
+}
+
+/// Starts a pod that exits straight away and waits for it to succeed.
+async fn completed_pod(pods: &Api<Pod>, name: &str, stale: bool, protected: bool) {
+    let pod: Pod = serde_json::from_value(json!({
+        "metadata": {
+            "name": name,
+            "labels": { "shopvac.io/test": if stale { "stale" } else { "fresh" } },
+            "annotations": { "shopvac.io/protect": protected.to_string() },
+        },
+        "spec": {
+            "restartPolicy": "Never",
+            "containers": [{ "name": "main", "image": "busybox", "command": ["true"] }],
+        },
+    }))
+    .unwrap();
+    pods.create(&PostParams::default(), &pod)
+        .await
+        .expect("Unable to create pod");
+
+    for _ in 0..120 {
+        let phase = pods
+            .get(name)
+            .await
+            .ok()
+            .and_then(|p| p.status)
+            .and_then(|s| s.phase);
+        if phase.as_deref() == Some("Succeeded") {
+            return;
+        }
+        time::sleep(time::Duration::from_millis(500)).await;
+    }
+    panic!("pod {} never completed", name);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deletes_matching_pods_and_keeps_protected_ones() {
+    let client = match cluster().await {
+        Some(client) => client,
+        None => return,
+    };
+    let scratch = Scratch::create(client.clone()).await;
+    let pods: Api<Pod> = Api::namespaced(client.clone(), &scratch.name);
+
+    completed_pod(&pods, "stale", true, false).await;
+    completed_pod(&pods, "stale-protected", true, true).await;
+    completed_pod(&pods, "fresh", false, false).await;
+
+    // creation timestamps can't be backdated, so the age filter is opened up
+    // and the label stands in for "old enough"
+    let options = ScanOptions {
+        label_selector: Some("shopvac.io/test=stale".to_string()),
+        not_labels: Vec::new(),
+        field_selector: None,
+        older_than: Duration::hours(-1),
+        created_after: None,
+        exclude_namespace_pattern: "(openshift.*)|(kube.*)".to_string(),
+        allow_system_namespaces: false,
+        annotation_selectors: Vec::new(),
+        phases: vec![PodPhase::Succeeded],
+        reasons: Vec::new(),
+        image_pattern: None,
+        exclude_pod_pattern: None,
+        owner_kinds: Vec::new(),
+        naked_only: false,
+        protection_annotation: Some("shopvac.io/protect".to_string()),
+        chunk_size: 500,
+        explain: false,
+        completed_for: None,
+    };
+
+    let targets = shopvac::scan_pods(&pods, &options).await.unwrap();
+    let names: Vec<String> = targets.iter().map(|t| t.name()).collect();
+    assert_eq!(names, vec!["stale"]);
+
+    let report = shopvac::delete_pods(
+        client,
+        &targets,
+        DeleteOptions::default(),
+        futures::future::pending(),
+    )
+    .await;
+    // pods in their grace period come back as terminating
+    assert!(report.outcomes.iter().all(|(_, o)| o.is_deleted()));
+
+    let mut remaining = Vec::new();
+    for _ in 0..60 {
+        remaining = pods
+            .list(&ListParams::default())
+            .await
+            .unwrap()
+            .iter()
+            .map(|p| p.name())
+            .collect();
+        if !remaining.iter().any(|n| n == "stale") {
+            break;
+        }
+        time::sleep(time::Duration::from_millis(500)).await;
+    }
+    remaining.sort();
+    assert_eq!(remaining, vec!["fresh", "stale-protected"]);
+}